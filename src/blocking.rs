@@ -0,0 +1,288 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{AuthError, AuthMethod, InvalidAuthParam, Member, ReservedAuthParam, TokenResponse};
+use crate::nonblocking;
+use crate::pkce::InvalidVerifierLength;
+use crate::session::Session;
+
+
+/// Holds the application credentials and the CSRF token of a single
+/// authorization flow. It drives `nonblocking::LinkedInAuthClient` on a
+/// current-thread tokio runtime of its own, so like `reqwest::blocking` it
+/// must not be used from within an async context.
+#[derive(Debug, Clone)]
+pub struct LinkedInAuthClient {
+    inner: nonblocking::LinkedInAuthClient,
+    // Kept for the lifetime of the client: pooled connections belong to the
+    // runtime that opened them.
+    runtime: Arc<Runtime>,
+}
+
+impl LinkedInAuthClient {
+    /// Starts a `LinkedInAuthClientBuilder`, an alternative to `new` and the
+    /// `with_*` methods.
+    pub fn builder() -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder::default()
+    }
+
+    /// # Panics
+    ///
+    /// When the tokio runtime cannot be started.
+    pub fn new(client_id: &str, client_secret: &str,
+               redirect_url: &str, scopes: &[&str]) -> LinkedInAuthClient {
+        let inner = nonblocking::LinkedInAuthClient::new(client_id, client_secret, redirect_url, scopes);
+        LinkedInAuthClient::wrap(inner)
+    }
+
+    fn wrap(inner: nonblocking::LinkedInAuthClient) -> LinkedInAuthClient {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("cannot start the tokio runtime of the blocking client");
+        LinkedInAuthClient { inner, runtime: Arc::new(runtime) }
+    }
+
+    fn map<F>(self, with: F) -> LinkedInAuthClient
+        where F: FnOnce(nonblocking::LinkedInAuthClient) -> nonblocking::LinkedInAuthClient {
+        LinkedInAuthClient { inner: with(self.inner), runtime: self.runtime }
+    }
+
+    fn try_map<F, E>(self, with: F) -> Result<LinkedInAuthClient, E>
+        where F: FnOnce(nonblocking::LinkedInAuthClient) -> Result<nonblocking::LinkedInAuthClient, E> {
+        Ok(LinkedInAuthClient { inner: with(self.inner)?, runtime: self.runtime })
+    }
+
+    /// Makes every request to LinkedIn go through `http`, so timeouts and
+    /// other settings configured on it apply to the whole flow.
+    pub fn with_http_client(self, http: Client) -> LinkedInAuthClient {
+        self.map(|inner| inner.with_http_client(http))
+    }
+
+    /// Points the client at other authorization and token endpoints, e.g.
+    /// a mock server.
+    pub fn with_endpoints(self, auth_url: &str, token_url: &str) -> Result<LinkedInAuthClient, AuthError> {
+        self.try_map(|inner| inner.with_endpoints(auth_url, token_url))
+    }
+
+    /// Points `revoke` at another revocation endpoint.
+    pub fn with_revoke_url(self, revoke_url: &str) -> Result<LinkedInAuthClient, AuthError> {
+        self.try_map(|inner| inner.with_revoke_url(revoke_url))
+    }
+
+    /// Points the profile requests made by `fetch_member` at another API
+    /// base URL.
+    pub fn with_api_url(self, api_url: &str) -> Result<LinkedInAuthClient, AuthError> {
+        self.try_map(|inner| inner.with_api_url(api_url))
+    }
+
+    /// Sets how many times a request is repeated after a connection failure,
+    /// a timeout or a 429/5xx response, 3 by default. The code exchange is
+    /// only repeated when it could not connect.
+    pub fn with_max_retries(self, max_retries: u32) -> LinkedInAuthClient {
+        self.map(|inner| inner.with_max_retries(max_retries))
+    }
+
+    /// Waits a random time between zero and the exponential backoff before
+    /// each retry, so clients failing together do not retry together.
+    pub fn with_retry_jitter(self, retry_jitter: bool) -> LinkedInAuthClient {
+        self.map(|inner| inner.with_retry_jitter(retry_jitter))
+    }
+
+    /// Sends the client credentials in a Basic `Authorization` header instead
+    /// of the form body with `AuthMethod::Basic`.
+    pub fn with_auth_method(self, auth_method: AuthMethod) -> LinkedInAuthClient {
+        self.map(|inner| inner.with_auth_method(auth_method))
+    }
+
+    /// Sets how much life a token must have left for `ensure_fresh` to
+    /// return it unchanged, 5 minutes by default.
+    pub fn with_refresh_buffer(self, buffer: Duration) -> LinkedInAuthClient {
+        self.map(|inner| inner.with_refresh_buffer(buffer))
+    }
+
+    /// Enables PKCE: the authorization URL carries an S256 code challenge and
+    /// the code exchange sends the verifier instead of the client secret.
+    pub fn with_pkce(self, verifier_length: usize) -> Result<LinkedInAuthClient, InvalidVerifierLength> {
+        self.try_map(|inner| inner.with_pkce(verifier_length))
+    }
+
+    /// Sends `prompt` with the authorization URL, e.g. `consent` to ask the
+    /// member to approve the scopes again.
+    pub fn with_prompt(self, prompt: &str) -> Result<LinkedInAuthClient, InvalidAuthParam> {
+        self.try_map(|inner| inner.with_prompt(prompt))
+    }
+
+    /// Sends `display` with the authorization URL, e.g. `popup` for a
+    /// sign in page sized for a popup window.
+    pub fn with_display(self, display: &str) -> Result<LinkedInAuthClient, InvalidAuthParam> {
+        self.try_map(|inner| inner.with_display(display))
+    }
+
+    /// Appends a custom parameter to the authorization URL, after the ones
+    /// the client manages itself. Those in `MANAGED_AUTH_PARAMS` are rejected.
+    pub fn with_auth_param(self, name: &str, value: &str) -> Result<LinkedInAuthClient, ReservedAuthParam> {
+        self.try_map(|inner| inner.with_auth_param(name, value))
+    }
+
+    /// Replaces the generated `state` value, so a code obtained with the URL
+    /// of an earlier run can still be exchanged.
+    pub fn with_csrf(self, csrf: &str) -> LinkedInAuthClient {
+        self.map(|inner| inner.with_csrf(csrf))
+    }
+
+    /// Restores the `state`, PKCE verifier and nonce of the run that generated
+    /// the authorization URL, so that run's code can be exchanged.
+    pub fn with_session(self, session: &Session) -> LinkedInAuthClient {
+        self.map(|inner| inner.with_session(session))
+    }
+
+    /// The values `with_session` needs to exchange a code for the current
+    /// authorization URL in another process.
+    pub fn session(&self) -> Session {
+        self.inner.session()
+    }
+
+    /// The `state` value sent with the authorization URL.
+    pub fn csrf(&self) -> &str {
+        self.inner.csrf()
+    }
+
+    /// The scopes requested with the authorization URL.
+    pub fn scopes(&self) -> &[String] {
+        self.inner.scopes()
+    }
+
+    /// The OpenID Connect `nonce` sent with the authorization URL, only set
+    /// when `openid` is among the scopes.
+    pub fn nonce(&self) -> Option<&str> {
+        self.inner.nonce()
+    }
+
+    pub fn authorization_url(&self) -> String {
+        self.inner.authorization_url()
+    }
+
+    /// Exchanges the authorization code for an access token, checking that
+    /// `state` is the one this client generated and, for OpenID logins, that
+    /// the `id_token` carries its nonce.
+    pub fn exchange_code(&self, code: &str, state: &str) -> Result<TokenResponse, AuthError> {
+        self.runtime.block_on(self.inner.exchange_code(code, state))
+    }
+
+    /// The `curl` command for the request `exchange_code` sends, see
+    /// `curl_command`.
+    pub fn exchange_code_curl(&self, code: &str, show_secrets: bool) -> String {
+        self.inner.exchange_code_curl(code, show_secrets)
+    }
+
+    /// Obtains an application access token with the two-legged
+    /// client_credentials grant, no member sign in is involved.
+    pub fn client_credentials(&self) -> Result<TokenResponse, AuthError> {
+        self.runtime.block_on(self.inner.client_credentials())
+    }
+
+    /// Obtains a new access token with a previously issued refresh token.
+    pub fn refresh(&self, refresh_token: &str) -> Result<TokenResponse, AuthError> {
+        self.runtime.block_on(self.inner.refresh(refresh_token))
+    }
+
+    /// Returns `token` while it has more than the refresh buffer of life
+    /// left, otherwise a new token obtained with its refresh token.
+    pub fn ensure_fresh(&self, token: &TokenResponse) -> Result<TokenResponse, AuthError> {
+        self.runtime.block_on(self.inner.ensure_fresh(token))
+    }
+
+    /// Reads the member behind `access_token`, from `userinfo` when the
+    /// client asks for `openid` and from `me` otherwise.
+    pub fn fetch_member(&self, access_token: &str) -> Result<Member, AuthError> {
+        self.runtime.block_on(self.inner.fetch_member(access_token))
+    }
+
+    /// Checks that the member behind `access_token` holds an approved role in
+    /// `organization`, an `urn:li:organization:<id>` URN.
+    pub fn check_organization(&self, access_token: &str, organization: &str) -> Result<(), AuthError> {
+        self.runtime.block_on(self.inner.check_organization(access_token, organization))
+    }
+
+    /// Invalidates an access or refresh token issued to this application.
+    pub fn revoke(&self, token: &str) -> Result<(), AuthError> {
+        self.runtime.block_on(self.inner.revoke(token))
+    }
+}
+
+
+/// Builds a `LinkedInAuthClient` field by field, see
+/// `nonblocking::LinkedInAuthClientBuilder` for the rules.
+///
+/// ```
+/// use lin_auth::LinkedInAuthClient;
+///
+/// let client = LinkedInAuthClient::builder()
+///     .client_id("client-id")
+///     .client_secret("client-secret")
+///     .redirect_url("https://localhost:8000")
+///     .scope("r_ads")
+///     .scope("r_ads_reporting")
+///     .timeout(std::time::Duration::from_secs(10))
+///     .build()?;
+///
+/// assert!(client.authorization_url().contains("scope=r_ads+r_ads_reporting"));
+/// # Ok::<(), lin_auth::AuthError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LinkedInAuthClientBuilder(nonblocking::LinkedInAuthClientBuilder);
+
+impl LinkedInAuthClientBuilder {
+    pub fn client_id(self, client_id: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.client_id(client_id))
+    }
+
+    pub fn client_secret(self, client_secret: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.client_secret(client_secret))
+    }
+
+    pub fn redirect_url(self, redirect_url: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.redirect_url(redirect_url))
+    }
+
+    /// Adds a scope, call it once per scope.
+    pub fn scope(self, scope: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.scope(scope))
+    }
+
+    /// Timeout of every request made to LinkedIn.
+    pub fn timeout(self, timeout: Duration) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.timeout(timeout))
+    }
+
+    /// An `http://` or `socks5://` proxy for every request.
+    pub fn proxy(self, proxy: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.proxy(proxy))
+    }
+
+    pub fn auth_url(self, auth_url: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.auth_url(auth_url))
+    }
+
+    pub fn token_url(self, token_url: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.token_url(token_url))
+    }
+
+    /// One of `PROMPT_VALUES`, checked by `build`.
+    pub fn prompt(self, prompt: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.prompt(prompt))
+    }
+
+    /// One of `DISPLAY_VALUES`, checked by `build`.
+    pub fn display(self, display: &str) -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder(self.0.display(display))
+    }
+
+    pub fn build(self) -> Result<LinkedInAuthClient, AuthError> {
+        Ok(LinkedInAuthClient::wrap(self.0.build()?))
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{AuthError, TokenResponse};
+use crate::files::write_private_file;
+
+/// Keyring service under which the tokens are stored, the account is the
+/// client id.
+pub const KEYRING_SERVICE: &str = "linkedin-auth";
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedToken {
+    pub token: TokenResponse,
+}
+
+impl CachedToken {
+    pub fn is_expired(&self) -> bool {
+        self.token.is_expired()
+    }
+}
+
+
+#[derive(Debug)]
+enum Storage {
+    File(PathBuf),
+    Keyring(String),
+}
+
+
+/// Tokens stored between invocations, keyed by `cache_key`.
+#[derive(Debug)]
+pub struct TokenCache {
+    storage: Storage,
+    entries: HashMap<String, CachedToken>,
+}
+
+impl TokenCache {
+    /// `~/.config/linkedin-auth/tokens.json` or its platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("linkedin-auth").join("tokens.json"))
+    }
+
+    /// Loads the cache at `path`, a missing file is an empty cache.
+    pub fn load(path: &Path) -> Result<TokenCache, AuthError> {
+        let entries = match fs::read(path) {
+            Ok(contents) => serde_json::from_slice(&contents).map_err(AuthError::CorruptCache)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(TokenCache { storage: Storage::File(path.to_path_buf()), entries })
+    }
+
+    /// Loads the tokens of `client_id` from the OS keyring (Keychain,
+    /// Credential Manager or Secret Service) instead of a plaintext file.
+    pub fn load_keyring(client_id: &str) -> Result<TokenCache, AuthError> {
+        let entries = match Entry::new(KEYRING_SERVICE, client_id)?.get_password() {
+            Ok(contents) => serde_json::from_str(&contents).map_err(AuthError::CorruptCache)?,
+            Err(keyring::Error::NoEntry) => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(TokenCache { storage: Storage::Keyring(client_id.to_string()), entries })
+    }
+
+    /// Returns the token stored under `key` unless it has expired, with
+    /// `expires_in` counting from now rather than from when it was issued.
+    pub fn get(&self, key: &str) -> Option<TokenResponse> {
+        let entry = self.entries.get(key)?;
+        if entry.is_expired() {
+            return None;
+        }
+
+        let mut token = entry.token.clone();
+        if let Some(seconds_left) = token.seconds_left() {
+            token.expires_in = Some(seconds_left);
+        }
+        Some(token)
+    }
+
+    /// The entry stored under `key`, expired or not.
+    pub fn entry(&self, key: &str) -> Option<&CachedToken> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, key: &str, token: &TokenResponse) {
+        let mut token = token.clone();
+        token.expires_at = token.expires_at.or_else(|| token.expires_in.map(|seconds| unix_now() + seconds));
+        self.entries.insert(key.to_string(), CachedToken { token });
+    }
+
+    /// Drops the entries holding `token` as their access or refresh token,
+    /// returns whether any was found.
+    pub fn remove_token(&mut self, token: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| {
+            entry.token.access_token != token && entry.token.refresh_token.as_deref() != Some(token)
+        });
+        self.entries.len() != before
+    }
+
+    pub fn save(&self) -> Result<(), AuthError> {
+        match &self.storage {
+            Storage::File(path) => {
+                let contents = serde_json::to_vec_pretty(&self.entries).map_err(AuthError::CorruptCache)?;
+                write_private_file(path, &contents)?;
+            },
+            Storage::Keyring(client_id) => {
+                Entry::new(KEYRING_SERVICE, client_id)?
+                    .set_password(&serde_json::to_string(&self.entries).map_err(AuthError::CorruptCache)?)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+
+/// Hash of the client id and the sorted scope list, so the same app with the
+/// same permissions reuses its token. Config profiles get a namespace of
+/// their own.
+pub fn cache_key(profile: Option<&str>, client_id: &str, scopes: &[&str]) -> String {
+    let mut scopes = scopes.to_vec();
+    scopes.sort_unstable();
+    scopes.dedup();
+
+    let mut input = format!("{}\n{}", client_id, scopes.join(" "));
+    if let Some(profile) = profile {
+        input = format!("{}\n{}", profile, input);
+    }
+    format!("{:x}", Sha256::digest(input.as_bytes()))
+}
+
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or_default()
+}
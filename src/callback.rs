@@ -0,0 +1,313 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rcgen::{CertifiedKey, generate_simple_self_signed};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use rustls::crypto::ring;
+use rustls::pki_types::PrivateKeyDer;
+use tracing::debug;
+use url::{Host, Url};
+
+use crate::AuthError;
+use crate::redirect::parse_redirect;
+
+const CALLBACK_PAGE: &str = "<html><body><h3>Authorization code received.</h3>\
+                             <p>You may close this tab and return to the terminal.</p>\
+                             </body></html>";
+const ERROR_PAGE: &str = "<html><body><h3>Authorization failed.</h3>\
+                          <p>LinkedIn reported an error, see the terminal for details.</p>\
+                          </body></html>";
+const CALLBACK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Browsers open speculative connections that never send a request, so a
+// silent connection must not hold up the real one for long.
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many requests other than the redirect `CallbackServer::wait` answers
+/// before giving up, see `CallbackServer::with_max_requests`.
+pub const DEFAULT_MAX_REQUESTS: usize = 50;
+
+
+#[derive(Debug)]
+pub struct CallbackTimeout;
+
+impl fmt::Display for CallbackTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out waiting for the authorization redirect")
+    }
+}
+
+impl Error for CallbackTimeout {}
+
+
+/// The callback server answered as many requests without the redirect as it
+/// was allowed to.
+#[derive(Debug)]
+pub struct TooManyCallbackRequests(pub usize);
+
+impl fmt::Display for TooManyCallbackRequests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = if self.0 == 1 { "" } else { "s" };
+        write!(f, "gave up after {} request{} to the local server without the authorization redirect, \
+                   pass --max-callback-requests to allow more", self.0, plural)
+    }
+}
+
+impl Error for TooManyCallbackRequests {}
+
+
+#[derive(Debug)]
+pub struct MissingCode;
+
+impl fmt::Display for MissingCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the redirect request does not contain an authorization code")
+    }
+}
+
+impl Error for MissingCode {}
+
+
+#[derive(Debug)]
+pub struct PortInUse(pub u16);
+
+impl fmt::Display for PortInUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "port {} is already in use, pick another port for the redirect URL \
+                   (and register it with the application)", self.0)
+    }
+}
+
+impl Error for PortInUse {}
+
+
+#[derive(Debug)]
+pub struct UnusablePort(pub u16);
+
+impl fmt::Display for UnusablePort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the callback server cannot listen on port {}, pick a port from 1 to 65535", self.0)
+    }
+}
+
+impl Error for UnusablePort {}
+
+
+#[derive(Debug)]
+pub struct InvalidListenAddr(pub String);
+
+impl fmt::Display for InvalidListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid listen address '{}', expected 'ip:port' such as '0.0.0.0:8000'", self.0)
+    }
+}
+
+impl Error for InvalidListenAddr {}
+
+
+/// Where the local server listens for the redirect, `tls` is set for
+/// `https://` redirect URLs.
+#[derive(Debug, Clone, Copy)]
+pub struct CallbackAddress {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub tls: bool,
+}
+
+
+fn redirect_tls(url: &Url) -> Option<bool> {
+    match url.scheme() {
+        "http" => Some(false),
+        "https" => Some(true),
+        _ => None,
+    }
+}
+
+
+/// Returns the address to listen on when the redirect URL points at
+/// localhost or an IP address of this machine, such as `0.0.0.0`.
+pub fn callback_address(redirect_url: &str) -> Option<CallbackAddress> {
+    let url = Url::parse(redirect_url).ok()?;
+    let tls = redirect_tls(&url)?;
+    let ip = match url.host()? {
+        Host::Domain("localhost") => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Host::Domain(_) => return None,
+        Host::Ipv4(ip) => IpAddr::V4(ip),
+        Host::Ipv6(ip) => IpAddr::V6(ip),
+    };
+    Some(CallbackAddress { ip, port: url.port_or_known_default()?, tls })
+}
+
+
+/// Parses an `ip:port` to listen on instead of the redirect URL's host, e.g.
+/// behind a tunnel. The scheme of `redirect_url` still decides about TLS.
+pub fn listen_address(redirect_url: &str, listen_addr: &str)
+                          -> Result<CallbackAddress, InvalidListenAddr> {
+    let invalid = || InvalidListenAddr(listen_addr.to_string());
+    let address: SocketAddr = listen_addr.parse().map_err(|_| invalid())?;
+    let tls = Url::parse(redirect_url).ok().as_ref().and_then(redirect_tls).unwrap_or_default();
+    Ok(CallbackAddress { ip: address.ip(), port: address.port(), tls })
+}
+
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+
+fn accept_before(listener: &TcpListener, deadline: Option<Instant>)
+                     -> Result<TcpStream, Box<dyn Error>> {
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => return Ok(stream),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(CallbackTimeout.into());
+                }
+                thread::sleep(CALLBACK_POLL_INTERVAL);
+            },
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+
+// A fresh self-signed certificate for localhost, the browser has to be told
+// to accept it.
+fn tls_config() -> Result<Arc<ServerConfig>, Box<dyn Error>> {
+    let CertifiedKey { cert, key_pair } =
+        generate_simple_self_signed(vec!["localhost".to_string(), "127.0.0.1".to_string()])?;
+    let key = PrivateKeyDer::Pkcs8(key_pair.serialize_der().into());
+
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.der().clone()], key)?;
+    Ok(Arc::new(config))
+}
+
+
+// Answers a request on the callback server and returns the code, or the
+// OAuth error LinkedIn redirected with, when the redirect carries `csrf` as
+// its state. Anything else, like the browser asking for `/favicon.ico`, gets
+// a 404.
+fn handle_request<S: Read + Write>(stream: &mut S, csrf: &str)
+                                      -> Result<Option<Result<String, AuthError>>, Box<dyn Error>> {
+    let mut request_line = String::new();
+    BufReader::new(&mut *stream).read_line(&mut request_line)?;
+
+    let target = request_line.split_whitespace().nth(1).ok_or(MissingCode)?;
+    let request_url = Url::parse("http://localhost")?.join(target)?;
+
+    let outcome = match parse_redirect(request_url.as_str()) {
+        _ if query_param(&request_url, "state").as_deref() != Some(csrf) => None,
+        Ok(params) => Some(Ok(params.code)),
+        Err(err @ AuthError::OAuth { .. }) => Some(Err(err)),
+        Err(_) => None,
+    };
+    let (status, body) = match &outcome {
+        Some(Ok(_)) => ("200 OK", CALLBACK_PAGE),
+        Some(Err(_)) => ("200 OK", ERROR_PAGE),
+        None => ("404 Not Found", "Not found."),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    )?;
+    stream.flush()?;
+
+    if outcome.is_none() {
+        debug!("ignoring a callback request for {}", request_url.path());
+    }
+    Ok(outcome)
+}
+
+
+/// The local server capturing the redirect. Binding it before the URL is
+/// shown means a taken port fails the run before a code can get lost.
+#[derive(Debug)]
+pub struct CallbackServer {
+    listener: TcpListener,
+    tls: Option<Arc<ServerConfig>>,
+    max_requests: usize,
+}
+
+impl CallbackServer {
+    /// Starts listening on `address`, failing with `PortInUse` when another
+    /// process holds the port.
+    pub fn bind(address: CallbackAddress) -> Result<CallbackServer, Box<dyn Error>> {
+        // Port 0 would listen on a random port the redirect cannot reach.
+        if address.port == 0 {
+            return Err(UnusablePort(address.port).into());
+        }
+        let listener = match TcpListener::bind((address.ip, address.port)) {
+            Ok(listener) => listener,
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => return Err(PortInUse(address.port).into()),
+            Err(err) => return Err(err.into()),
+        };
+        listener.set_nonblocking(true)?;
+        let tls = match address.tls {
+            true => Some(tls_config()?),
+            false => None,
+        };
+        Ok(CallbackServer { listener, tls, max_requests: DEFAULT_MAX_REQUESTS })
+    }
+
+    /// Makes `wait` give up with `TooManyCallbackRequests` once it skipped
+    /// `max_requests` requests or failed connections, so noise from browser
+    /// extensions cannot keep it waiting forever.
+    pub fn with_max_requests(mut self, max_requests: usize) -> CallbackServer {
+        self.max_requests = max_requests;
+        self
+    }
+
+    /// Waits for the authorization redirect and returns the received
+    /// `code`, or `AuthError::OAuth` when LinkedIn redirected with an error.
+    /// Requests without a code or with a state other than `csrf` are
+    /// answered with a 404 and skipped, up to the `with_max_requests` limit
+    /// that also counts connections failing before a request.
+    /// Without a timeout it waits forever.
+    pub fn wait(&self, csrf: &str, timeout: Option<Duration>) -> Result<String, Box<dyn Error>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut skipped = 0;
+
+        // Connections that fail before sending a request are skipped, browsers
+        // drop the first TLS one when they show the certificate warning.
+        loop {
+            let mut stream = accept_before(&self.listener, deadline)?;
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(REQUEST_READ_TIMEOUT))?;
+
+            let result = match &self.tls {
+                Some(config) => ServerConnection::new(config.clone())
+                    .map_err(Into::into)
+                    .and_then(|connection| handle_request(&mut StreamOwned::new(connection, stream), csrf)),
+                None => handle_request(&mut stream, csrf),
+            };
+
+            match result {
+                Ok(Some(outcome)) => return outcome.map_err(Into::into),
+                Ok(None) => {},
+                Err(err) => debug!("skipping a callback connection: {}", err),
+            }
+            skipped += 1;
+            if skipped >= self.max_requests {
+                return Err(TooManyCallbackRequests(skipped).into());
+            }
+        }
+    }
+}
+
+
+/// Binds a `CallbackServer` on `address` and waits for the redirect on it.
+pub fn wait_for_callback(address: CallbackAddress, csrf: &str, timeout: Option<Duration>)
+                             -> Result<String, Box<dyn Error>> {
+    CallbackServer::bind(address)?.wait(csrf, timeout)
+}
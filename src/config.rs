@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Looked up in the working directory when `--config` is not given.
+pub const DEFAULT_CONFIG_PATH: &str = "linkedin-auth.toml";
+pub const DEFAULT_PROFILE: &str = "default";
+
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot read the config file '{}': {}", self.path, self.reason)
+    }
+}
+
+impl Error for ConfigError {}
+
+
+/// Values of the `[linkedin]` table or of a `[profiles.<name>]` table, every
+/// field is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub redirect_url: Option<String>,
+    pub scopes: Option<Vec<String>>,
+    /// Name of the profile the values were taken from, if any.
+    #[serde(skip)]
+    pub profile: Option<String>,
+    /// The `[[batch]]` entries of the file, shared by all profiles.
+    #[serde(skip)]
+    pub batch: Vec<BatchEntry>,
+}
+
+
+/// One token requested by the `batch` subcommand.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchEntry {
+    pub name: String,
+    pub scopes: Vec<String>,
+    /// File the token response is written to, `<name>.json` by default.
+    pub output: Option<String>,
+}
+
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    linkedin: Config,
+    profiles: HashMap<String, Config>,
+    batch: Vec<BatchEntry>,
+}
+
+
+impl Config {
+    /// Loads `profile` from the file, falling back to `[linkedin]` for the
+    /// fields the profile leaves out. Without a name the `default` profile is
+    /// used when the file has one.
+    pub fn load(path: &Path, profile: Option<&str>) -> Result<Config, ConfigError> {
+        let invalid = |reason: String| ConfigError { path: path.display().to_string(), reason };
+
+        let contents = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        let mut file: ConfigFile = toml::from_str(&contents).map_err(|err| invalid(err.to_string()))?;
+
+        let name = profile.unwrap_or(DEFAULT_PROFILE);
+        let selected = match file.profiles.remove(name) {
+            Some(selected) => selected,
+            None if profile.is_none() => return Ok(Config { batch: file.batch, ..file.linkedin }),
+            None => return Err(invalid(format!("there is no [profiles.{}] table", name))),
+        };
+
+        let base = file.linkedin;
+        Ok(Config {
+            client_id: selected.client_id.or(base.client_id),
+            client_secret: selected.client_secret.or(base.client_secret),
+            redirect_url: selected.redirect_url.or(base.redirect_url),
+            scopes: selected.scopes.or(base.scopes),
+            profile: Some(name.to_string()),
+            batch: file.batch,
+        })
+    }
+}
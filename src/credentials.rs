@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use serde::Deserialize;
+
+
+#[derive(Debug)]
+pub struct CredentialsFileError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for CredentialsFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot read the credentials file '{}': {}", self.path, self.reason)
+    }
+}
+
+impl Error for CredentialsFileError {}
+
+
+/// The credentials file can be read by other users than its owner.
+#[derive(Debug)]
+pub struct InsecurePermissions {
+    pub path: String,
+    pub mode: u32,
+}
+
+impl fmt::Display for InsecurePermissions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "permissions {:04o} for '{}' are too open, the credentials file must only be \
+                   accessible by its owner, run chmod 600 on it", self.mode, self.path)
+    }
+}
+
+impl Error for InsecurePermissions {}
+
+
+/// The client credentials of a `--credentials-file`, either TOML
+/// (`client_id = "..."`) or `KEY=VALUE` lines as in a `.env` file, where the
+/// keys may be upper case and carry a `LINKEDIN_` prefix.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+
+impl Credentials {
+    /// Loads the file at `path`, refusing it like ssh does with private keys
+    /// when it is readable or writable by the group or others.
+    pub fn load(path: &Path) -> Result<Credentials, Box<dyn Error>> {
+        let invalid = |reason: String| CredentialsFileError { path: path.display().to_string(), reason };
+
+        #[cfg(unix)]
+        {
+            let mode = fs::metadata(path).map_err(|err| invalid(err.to_string()))?.permissions().mode() & 0o777;
+            if mode & 0o077 != 0 {
+                return Err(InsecurePermissions { path: path.display().to_string(), mode }.into());
+            }
+        }
+
+        let contents = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        Ok(Credentials::parse(&contents).map_err(invalid)?)
+    }
+
+    pub fn parse(contents: &str) -> Result<Credentials, String> {
+        if let Ok(credentials) = toml::from_str(contents) {
+            return Ok(credentials);
+        }
+
+        // The lines may hold the secret, so errors only name their number.
+        let mut credentials = Credentials::default();
+        for (number, line) in contents.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| format!("line {} is not a KEY=VALUE line", number + 1))?;
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+            match key.strip_prefix("linkedin_").unwrap_or(&key) {
+                "client_id" => credentials.client_id = Some(value),
+                "client_secret" => credentials.client_secret = Some(value),
+                _ => return Err(format!("line {} has an unknown key, expected client_id and client_secret",
+                                        number + 1)),
+            }
+        }
+        Ok(credentials)
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::files::replace_file_atomic;
+
+
+// Double quotes keep values with spaces or `#` intact, dotenv parsers
+// unescape `\"` and `\\` inside them.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+
+fn line_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+
+/// Sets `values` in the dotenv `contents`: existing keys are replaced where
+/// they are, new ones are appended and every other line is kept.
+pub fn update_dotenv(contents: &str, values: &[(&str, String)]) -> String {
+    let mut written = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in contents.lines() {
+        match values.iter().find(|(key, _)| line_key(line) == Some(*key)) {
+            Some((key, _)) if written.contains(key) => {},
+            Some((key, value)) => {
+                let export = if line.trim_start().starts_with("export ") { "export " } else { "" };
+                lines.push(format!("{}{}={}", export, key, quote(value)));
+                written.push(*key);
+            },
+            None => lines.push(line.to_string()),
+        }
+    }
+    for (key, value) in values {
+        if !written.contains(key) {
+            lines.push(format!("{}={}", key, quote(value)));
+        }
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    updated
+}
+
+
+/// Updates the dotenv file at `path` in place, a missing file is created
+/// readable by its owner only while an existing one keeps its permissions.
+/// The file is replaced atomically so a crash cannot leave it half written.
+pub fn write_dotenv(path: &Path, values: &[(&str, String)]) -> io::Result<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    replace_file_atomic(path, update_dotenv(&contents, values).as_bytes())
+}
@@ -0,0 +1,56 @@
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions, Permissions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+
+
+/// Writes `contents` to `path`, making the file readable by its owner only
+/// on Unix. Missing parent directories are created.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    file.set_permissions(Permissions::from_mode(0o600))?;
+
+    file.write_all(contents)
+}
+
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so readers never see a partial file. An existing file keeps its
+/// permissions, a new one is made readable by its owner only on Unix.
+pub fn replace_file_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let permissions = match fs::metadata(path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    replace_file(path, contents, permissions)
+}
+
+
+fn replace_file(path: &Path, contents: &[u8], permissions: Option<Permissions>) -> io::Result<()> {
+    let file_name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name"))?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    write_private_file(&temp_path, contents)
+        .and_then(|_| permissions.map_or(Ok(()), |permissions| fs::set_permissions(&temp_path, permissions)))
+        .and_then(|_| fs::rename(&temp_path, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })
+}
@@ -0,0 +1,614 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use base64::encode_config;
+use rand::{Rng, thread_rng};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, trace};
+use url::Url;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
+pub mod callback;
+pub mod config;
+pub mod credentials;
+pub mod dotenv;
+pub mod files;
+pub mod id_token;
+pub mod nonblocking;
+pub mod output;
+pub mod pkce;
+pub mod prompt;
+pub mod redact;
+pub mod redirect;
+pub mod retry;
+pub mod scopes;
+pub mod session;
+pub mod template;
+
+use id_token::decode_claims;
+use pkce::{InvalidVerifierLength, Pkce};
+use redact::redact;
+
+#[cfg(feature = "blocking")]
+pub use blocking::{LinkedInAuthClient, LinkedInAuthClientBuilder};
+
+pub const AUTH_URL: &str = "https://www.linkedin.com/oauth/v2/authorization";
+pub const ACCESS_TOKEN_URL: &str = "https://www.linkedin.com/oauth/v2/accessToken";
+pub const REVOKE_URL: &str = "https://www.linkedin.com/oauth/v2/revoke";
+pub const API_URL: &str = "https://api.linkedin.com/v2/";
+
+/// How much life a token must have left for `ensure_fresh` to keep it.
+pub const DEFAULT_REFRESH_BUFFER: Duration = Duration::from_secs(5 * 60);
+
+/// Values the authorization endpoint accepts for `prompt` and `display`.
+pub const PROMPT_VALUES: &[&str] = &["none", "login", "consent", "select_account"];
+pub const DISPLAY_VALUES: &[&str] = &["page", "popup", "touch", "wap"];
+/// Parameters of the authorization URL the client sets itself, custom ones
+/// must not override them.
+pub const MANAGED_AUTH_PARAMS: &[&str] = &[
+    "response_type", "client_id", "redirect_uri", "state", "scope", "nonce",
+    "code_challenge", "code_challenge_method", "prompt", "display",
+];
+
+// Random bytes behind the `state` value, 256 bits of entropy.
+const CSRF_BYTES: usize = 32;
+// How much of an unexpected response body is quoted in `AuthError`.
+const BODY_EXCERPT_BYTES: usize = 500;
+
+
+/// Everything the client and the token cache can fail with, so callers can
+/// tell network problems, LinkedIn's answers and invalid arguments apart.
+#[derive(Debug)]
+pub enum AuthError {
+    /// Sending a request to LinkedIn or reading its response failed.
+    Http(reqwest::Error),
+    /// A successful response is not the expected JSON. `body` is its start
+    /// with secrets redacted.
+    Json { status: StatusCode, body: String, source: serde_json::Error },
+    /// LinkedIn answered with an OAuth `error` code.
+    OAuth { error: String, description: String },
+    /// An error status without an OAuth error in the body.
+    Status(StatusCode),
+    /// The token response carries no `access_token`, `body` is redacted.
+    MissingAccessToken { status: StatusCode, body: String },
+    /// The profile endpoint rejected the token or returned no member.
+    Profile { status: StatusCode, message: String },
+    /// The token cannot act for `organization`: the organization endpoint
+    /// rejected it, or the member has no approved role there.
+    Organization { organization: String, status: StatusCode, message: String },
+    CsrfMismatch,
+    /// The redirect carries neither a code nor an OAuth error.
+    MissingCode,
+    NonceMismatch,
+    InvalidIdToken,
+    InvalidRedirectUrl(InvalidRedirectUrl),
+    InvalidUrl(url::ParseError),
+    InvalidAuthParam(InvalidAuthParam),
+    ReservedAuthParam(ReservedAuthParam),
+    InvalidVerifierLength(InvalidVerifierLength),
+    /// A required builder field was not set.
+    MissingField(&'static str),
+    /// `ensure_fresh` got a token about to expire without a refresh token.
+    MissingRefreshToken,
+    /// The token cache holds something other than the expected JSON.
+    CorruptCache(serde_json::Error),
+    Keyring(keyring::Error),
+    Io(io::Error),
+}
+
+impl AuthError {
+    fn json(status: StatusCode, body: &str, source: serde_json::Error) -> AuthError {
+        AuthError::Json { status, body: excerpt(body), source }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthError::Http(err) => write!(f, "{}", err),
+            AuthError::Json { status, body, .. } | AuthError::MissingAccessToken { status, body } => {
+                write!(f, "cannot retrieve access key value from the response (HTTP {}): {}", status, body)
+            },
+            AuthError::OAuth { error, description } if description.is_empty() => {
+                write!(f, "LinkedIn returned '{}'", error)
+            },
+            AuthError::OAuth { error, description } => {
+                write!(f, "LinkedIn returned '{}': {}", error, description)
+            },
+            AuthError::Status(status) => write!(f, "the token endpoint responded with HTTP status {}", status),
+            AuthError::Profile { status, message } => match *status {
+                StatusCode::UNAUTHORIZED => write!(f, "LinkedIn rejected the access token: {}", message),
+                StatusCode::FORBIDDEN => write!(
+                    f, "the access token lacks the scope to read the member profile, \
+                       request 'openid profile' or 'r_liteprofile': {}", message
+                ),
+                status => write!(f, "the profile request failed with HTTP status {}: {}", status, message),
+            },
+            AuthError::Organization { organization, status, message } => match *status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => write!(
+                    f, "the access token cannot read the roles of {}, request 'rw_organization_admin' \
+                       or the organization social scopes: {}", organization, message
+                ),
+                status if status.is_success() => write!(f, "{}", message),
+                status => write!(f, "the organization request failed with HTTP status {}: {}", status, message),
+            },
+            AuthError::CsrfMismatch => write!(f, "the returned state does not match the generated CSRF token"),
+            AuthError::MissingCode => write!(f, "{}", callback::MissingCode),
+            AuthError::NonceMismatch => {
+                write!(f, "the nonce claim of the id_token does not match the generated nonce")
+            },
+            AuthError::InvalidIdToken => write!(f, "{}", id_token::InvalidIdToken),
+            AuthError::InvalidRedirectUrl(err) => write!(f, "{}", err),
+            AuthError::InvalidUrl(err) => write!(f, "invalid endpoint URL: {}", err),
+            AuthError::InvalidAuthParam(err) => write!(f, "{}", err),
+            AuthError::ReservedAuthParam(err) => write!(f, "{}", err),
+            AuthError::InvalidVerifierLength(err) => write!(f, "{}", err),
+            AuthError::MissingField(field) => write!(f, "{} is required to build the client", field),
+            AuthError::MissingRefreshToken => {
+                write!(f, "the access token is about to expire and there is no refresh token to renew it")
+            },
+            AuthError::CorruptCache(err) => write!(f, "the token cache is not valid JSON: {}", err),
+            AuthError::Keyring(err) => write!(f, "{}", err),
+            AuthError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for AuthError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AuthError::Http(err) => Some(err),
+            AuthError::Json { source, .. } => Some(source),
+            AuthError::InvalidRedirectUrl(err) => Some(err),
+            AuthError::InvalidUrl(err) => Some(err),
+            AuthError::InvalidAuthParam(err) => Some(err),
+            AuthError::ReservedAuthParam(err) => Some(err),
+            AuthError::InvalidVerifierLength(err) => Some(err),
+            AuthError::CorruptCache(err) => Some(err),
+            AuthError::Keyring(err) => Some(err),
+            AuthError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for AuthError {
+    fn from(err: reqwest::Error) -> AuthError {
+        AuthError::Http(err)
+    }
+}
+
+impl From<url::ParseError> for AuthError {
+    fn from(err: url::ParseError) -> AuthError {
+        AuthError::InvalidUrl(err)
+    }
+}
+
+impl From<id_token::InvalidIdToken> for AuthError {
+    fn from(_: id_token::InvalidIdToken) -> AuthError {
+        AuthError::InvalidIdToken
+    }
+}
+
+impl From<InvalidRedirectUrl> for AuthError {
+    fn from(err: InvalidRedirectUrl) -> AuthError {
+        AuthError::InvalidRedirectUrl(err)
+    }
+}
+
+impl From<InvalidAuthParam> for AuthError {
+    fn from(err: InvalidAuthParam) -> AuthError {
+        AuthError::InvalidAuthParam(err)
+    }
+}
+
+impl From<ReservedAuthParam> for AuthError {
+    fn from(err: ReservedAuthParam) -> AuthError {
+        AuthError::ReservedAuthParam(err)
+    }
+}
+
+impl From<InvalidVerifierLength> for AuthError {
+    fn from(err: InvalidVerifierLength) -> AuthError {
+        AuthError::InvalidVerifierLength(err)
+    }
+}
+
+impl From<keyring::Error> for AuthError {
+    fn from(err: keyring::Error) -> AuthError {
+        AuthError::Keyring(err)
+    }
+}
+
+impl From<io::Error> for AuthError {
+    fn from(err: io::Error) -> AuthError {
+        AuthError::Io(err)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct InvalidRedirectUrl {
+    pub url: String,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidRedirectUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid redirect URL '{}': {}", self.url, self.reason)
+    }
+}
+
+impl Error for InvalidRedirectUrl {}
+
+
+#[derive(Debug)]
+pub struct InvalidAuthParam {
+    pub name: &'static str,
+    pub value: String,
+}
+
+impl fmt::Display for InvalidAuthParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let allowed = match self.name {
+            "prompt" => PROMPT_VALUES,
+            _ => DISPLAY_VALUES,
+        };
+        write!(f, "unknown {} value '{}', expected one of: {}", self.name, self.value, allowed.join(", "))
+    }
+}
+
+impl Error for InvalidAuthParam {}
+
+
+#[derive(Debug)]
+pub struct ReservedAuthParam(pub String);
+
+impl fmt::Display for ReservedAuthParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the authorization URL parameter '{}' is set by the client and cannot be overridden",
+               self.0)
+    }
+}
+
+impl Error for ReservedAuthParam {}
+
+
+/// Where the client credentials go in requests to the token and revocation
+/// endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuthMethod {
+    /// `client_id` and `client_secret` form fields.
+    #[default]
+    Body,
+    /// An `Authorization: Basic base64(client_id:client_secret)` header.
+    Basic,
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token_expires_in: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    /// Unix timestamp at which the access token expires, computed from
+    /// `expires_in` when the response is received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl TokenResponse {
+    /// Whether LinkedIn declared the token as a bearer token, the scheme API
+    /// calls authenticate with.
+    pub fn is_bearer(&self) -> bool {
+        self.token_type.as_deref().is_some_and(|token_type| token_type.eq_ignore_ascii_case("bearer"))
+    }
+
+    /// Whether the token expires within `buffer` from now. Tokens without a
+    /// known expiry never do.
+    pub fn expires_within(&self, buffer: Duration) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= cache::unix_now() + buffer.as_secs())
+    }
+
+    /// Whether the token has expired, never for tokens without a known
+    /// expiry.
+    pub fn is_expired(&self) -> bool {
+        self.expires_within(Duration::ZERO)
+    }
+
+    /// Seconds left until `expires_at`, unlike `expires_in` which counts from
+    /// when the token was issued.
+    pub fn seconds_left(&self) -> Option<u64> {
+        self.expires_at.map(|expires_at| expires_at.saturating_sub(cache::unix_now()))
+    }
+}
+
+
+/// The member a token was issued for, as returned by the profile endpoint.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub id: String,
+    pub name: String,
+}
+
+
+/// Checks that `redirect_url` can be registered with LinkedIn: an absolute
+/// `http`/`https` URL without a fragment.
+pub fn validate_redirect_url(redirect_url: &str) -> Result<Url, InvalidRedirectUrl> {
+    let invalid = |reason: String| InvalidRedirectUrl { url: redirect_url.to_string(), reason };
+
+    let url = Url::parse(redirect_url).map_err(|err| invalid(err.to_string()))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(invalid(format!("the scheme must be http or https, not {}", url.scheme())));
+    }
+    if url.fragment().is_some() {
+        return Err(invalid("the URL must not contain a fragment".to_string()));
+    }
+    Ok(url)
+}
+
+
+// The start of a response body with secrets redacted, for error messages.
+fn excerpt(body: &str) -> String {
+    let mut body = redact(body);
+    if body.len() > BODY_EXCERPT_BYTES {
+        let mut end = BODY_EXCERPT_BYTES;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+        body.push_str("...");
+    }
+    body
+}
+
+
+/// The `Authorization` header value carrying the client credentials with
+/// `AuthMethod::Basic`.
+pub fn basic_authorization(client_id: &str, client_secret: &str) -> String {
+    format!("Basic {}", encode_config(format!("{}:{}", client_id, client_secret), base64::STANDARD))
+}
+
+
+// Moves the client credentials out of the form into a Basic `Authorization`
+// header. Forms without a client secret, e.g. with PKCE, are left alone.
+fn place_credentials<'a>(params: &[(&'a str, &'a str)], method: AuthMethod)
+                         -> (Vec<(&'a str, &'a str)>, Option<String>) {
+    let field = |name: &str| params.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+    match (method, field("client_id"), field("client_secret")) {
+        (AuthMethod::Basic, Some(client_id), Some(client_secret)) => (
+            params.iter().filter(|(key, _)| *key != "client_id" && *key != "client_secret").copied().collect(),
+            Some(basic_authorization(client_id, client_secret)),
+        ),
+        _ => (params.to_vec(), None),
+    }
+}
+
+
+/// The `curl` command sending the form `params` to `url` the way the clients
+/// do, for reproducing a request by hand. Unless `show_secrets` is set, the
+/// client secret, the PKCE verifier and the Basic credentials read `***`.
+pub fn curl_command(url: &Url, params: &[(&str, &str)], method: AuthMethod, show_secrets: bool) -> String {
+    let (params, authorization) = place_credentials(params, method);
+
+    let mut command = format!("curl -X POST {}", shell_quote(url.as_str()));
+    if let Some(authorization) = authorization {
+        let authorization = match show_secrets {
+            true => authorization,
+            false => "Basic ***".to_string(),
+        };
+        command.push_str(&format!(" \\\n  -H {}", shell_quote(&format!("Authorization: {}", authorization))));
+    }
+    for (key, value) in params {
+        let value = match key {
+            "client_secret" | "code_verifier" if !show_secrets => "***",
+            _ => value,
+        };
+        command.push_str(&format!(" \\\n  --data-urlencode {}", shell_quote(&format!("{}={}", key, value))));
+    }
+    command
+}
+
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+
+fn generate_csrf() -> String {
+    let random_bytes: Vec<u8> = (0..CSRF_BYTES).map(|_| thread_rng().gen::<u8>()).collect();
+    encode_config(&random_bytes, base64::URL_SAFE_NO_PAD)
+}
+
+
+// OpenID Connect binds the id_token to the authorization request with a
+// nonce, it is only sent when `openid` is requested.
+fn generate_nonce(scopes: &[String]) -> Option<String> {
+    scopes.iter().any(|scope| scope == "openid").then(generate_csrf)
+}
+
+
+fn verify_nonce(nonce: Option<&str>, token: &TokenResponse) -> Result<(), AuthError> {
+    let (nonce, id_token) = match (nonce, &token.id_token) {
+        (Some(nonce), Some(id_token)) => (nonce, id_token),
+        _ => return Ok(()),
+    };
+    match decode_claims(id_token)?["nonce"].as_str() {
+        Some(claim) if claim == nonce => Ok(()),
+        _ => Err(AuthError::NonceMismatch),
+    }
+}
+
+
+// Sets one of the optional `prompt`/`display` parameters of the
+// authorization URL, replacing an earlier value.
+fn set_auth_param(params: &mut Vec<(String, String)>, name: &'static str, value: &str,
+                  allowed: &[&str]) -> Result<(), InvalidAuthParam> {
+    if !allowed.contains(&value) {
+        return Err(InvalidAuthParam { name, value: value.to_string() });
+    }
+    params.retain(|(key, _)| key != name);
+    params.push((name.to_string(), value.to_string()));
+    Ok(())
+}
+
+
+fn verify_state(csrf: &str, state: Option<&str>) -> Result<(), AuthError> {
+    match state {
+        Some(state) if state == csrf => Ok(()),
+        _ => Err(AuthError::CsrfMismatch),
+    }
+}
+
+
+fn parse_token_body(status: StatusCode, body: &str) -> Result<TokenResponse, AuthError> {
+    trace!("response body: {}", redact(body));
+
+    let data: Value = match serde_json::from_str(body) {
+        Ok(data) => data,
+        Err(_) if !status.is_success() => return Err(AuthError::Status(status)),
+        Err(err) => return Err(AuthError::json(status, body, err)),
+    };
+
+    if let Some(error) = oauth_error(&data) {
+        return Err(error);
+    }
+    if !status.is_success() {
+        return Err(AuthError::Status(status));
+    }
+
+    match &data["access_token"] {
+        Value::String(_) => {
+            let mut token: TokenResponse = serde_json::from_value(data)
+                .map_err(|err| AuthError::json(status, body, err))?;
+            token.expires_at = token.expires_in.map(|seconds| cache::unix_now() + seconds);
+            Ok(token)
+        },
+        _ => Err(AuthError::MissingAccessToken { status, body: excerpt(body) }),
+    }
+}
+
+
+// The revocation endpoint answers with an empty body on success.
+fn parse_revoke_body(status: StatusCode, body: &str) -> Result<(), AuthError> {
+    trace!("response body: {}", redact(body));
+
+    let data: Value = serde_json::from_str(body).unwrap_or_default();
+    if let Some(error) = oauth_error(&data) {
+        return Err(error);
+    }
+    match status.is_success() {
+        true => Ok(()),
+        false => Err(AuthError::Status(status)),
+    }
+}
+
+
+fn oauth_error(data: &Value) -> Option<AuthError> {
+    let error = data["error"].as_str()?;
+    let description = data["error_description"].as_str().unwrap_or_default();
+    Some(AuthError::OAuth { error: error.to_string(), description: description.to_string() })
+}
+
+
+// OpenID tokens read `userinfo`, the older profile scopes only allow `me`.
+fn profile_endpoint(api_url: &Url, scopes: &[String]) -> Result<Url, AuthError> {
+    let path = match scopes.iter().any(|scope| scope == "openid") {
+        true => "userinfo",
+        false => "me",
+    };
+    Ok(api_url.join(path)?)
+}
+
+
+fn parse_member_body(status: StatusCode, body: &str) -> Result<Member, AuthError> {
+    trace!("response body: {}", body);
+
+    let data: Value = serde_json::from_str(body).unwrap_or_default();
+    if !status.is_success() {
+        let message = data["message"].as_str().unwrap_or(body).to_string();
+        return Err(AuthError::Profile { status, message });
+    }
+
+    let field = |name: &str| data[name].as_str().unwrap_or_default();
+    let (id, name) = match &data["sub"] {
+        Value::String(sub) => (sub.as_str(), field("name").to_string()),
+        _ => (field("id"), format!("{} {}", field("localizedFirstName"), field("localizedLastName"))),
+    };
+    match id.is_empty() {
+        true => Err(AuthError::Profile { status, message: "the response names no member".to_string() }),
+        false => Ok(Member { id: id.to_string(), name: name.trim().to_string() }),
+    }
+}
+
+
+fn organization_acls_endpoint(api_url: &Url) -> Result<Url, AuthError> {
+    let mut url = api_url.join("organizationAcls")?;
+    url.query_pairs_mut().append_pair("q", "roleAssignee").append_pair("state", "APPROVED");
+    Ok(url)
+}
+
+
+// The roles of the member, `organization` (v2) or `organizationTarget` (the
+// versioned API) naming the organization of each.
+fn parse_organization_acls(status: StatusCode, body: &str, organization: &str) -> Result<(), AuthError> {
+    trace!("response body: {}", body);
+
+    let data: Value = serde_json::from_str(body).unwrap_or_default();
+    let error = |message: String| {
+        AuthError::Organization { organization: organization.to_string(), status, message }
+    };
+    if !status.is_success() {
+        return Err(error(data["message"].as_str().unwrap_or(body).to_string()));
+    }
+
+    let elements = data["elements"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let has_role = elements.iter().any(|acl| {
+        acl["organization"].as_str().or(acl["organizationTarget"].as_str()) == Some(organization)
+    });
+    match has_role {
+        true => Ok(()),
+        false => Err(error(format!("the member has no approved role in {}", organization))),
+    }
+}
+
+
+fn generate_auth_code_url(auth_url: &Url, client_id: &str, redirect_url: &str, permissions: &[String],
+                          csrf: &str, extra_params: &[(&str, &str)], pkce: Option<&Pkce>) -> String {
+
+    let permissions_str = permissions.join(" ");
+
+    let mut url = auth_url.clone();
+    url.query_pairs_mut()
+        .extend_pairs(
+            &[("response_type", "code"), ("client_id", client_id),
+              ("redirect_uri", redirect_url), ("state", csrf),
+              ("scope", &permissions_str)]
+        );
+
+    if let Some(pkce) = pkce {
+        url.query_pairs_mut()
+            .append_pair("code_challenge", pkce.challenge())
+            .append_pair("code_challenge_method", "S256");
+    }
+    url.query_pairs_mut().extend_pairs(extra_params);
+
+    debug!("authorization URL: {}", url);
+    url.as_str().to_string()
+}
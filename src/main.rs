@@ -1,173 +1,2482 @@
-use std::io;
+use std::env;
+use std::io::{self, IsTerminal};
 use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use base64::encode_config;
-use clap::{Arg, ArgMatches, App};
-use rand::{Rng, thread_rng};
-use reqwest::Error as ReqError;
-use reqwest::blocking::{Client};
-use serde_json::Value;
+use arboard::Clipboard;
+use chrono::{Local, TimeZone, Utc};
+use clap::{Arg, ArgAction, ArgMatches, ColorChoice, Command};
+use clap::builder::PossibleValuesParser;
+use clap::parser::ValueSource;
+use clap_complete::Shell;
+use dialoguer::MultiSelect;
+use indicatif::ProgressBar;
+use lin_auth::{ACCESS_TOKEN_URL, API_URL, AUTH_URL, DISPLAY_VALUES, PROMPT_VALUES, REVOKE_URL,
+               AuthError, AuthMethod, InvalidAuthParam, InvalidRedirectUrl, ReservedAuthParam,
+               TokenResponse, validate_redirect_url};
+use lin_auth::cache::{TokenCache, cache_key};
+use lin_auth::callback::{CallbackServer, CallbackTimeout, InvalidListenAddr, MissingCode, PortInUse,
+                         TooManyCallbackRequests, UnusablePort, callback_address, listen_address};
+use lin_auth::config::{Config, ConfigError, DEFAULT_CONFIG_PATH};
+use lin_auth::credentials::{Credentials, CredentialsFileError, InsecurePermissions};
+use lin_auth::dotenv::write_dotenv;
+use lin_auth::files::write_private_file;
+use lin_auth::id_token::decode_claims;
+use lin_auth::nonblocking::LinkedInAuthClient;
+use lin_auth::output::{MissingTokenField, Mode, Reporter, describe_duration, describe_expiry, token_variables};
+use lin_auth::pkce::InvalidVerifierLength;
+use lin_auth::prompt::{EmptyCode, EmptyCodeFile, Timeout, read_code, read_code_file, read_stdin_code};
+use lin_auth::redirect::RedirectParams;
+use lin_auth::redact::{mask_token, redact};
+use lin_auth::scopes::{SCOPE_CATEGORIES, ScopesFileError, UnknownScopes, load_app_products, load_scopes_file,
+                       missing_scopes, normalize_scopes, scope_set, split_scopes, validate_provisioned_scopes,
+                       validate_scopes};
+use lin_auth::session::Session;
+use lin_auth::template::check_template;
+use reqwest::{Client, Error as ReqError, Proxy, StatusCode};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Value, json};
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+use url::Url;
 
-const AUTH_URL: &str = "https://www.linkedin.com/oauth/v2/authorization";
-const ACCESS_TOKEN_URL: &str = "https://www.linkedin.com/oauth/v2/accessToken";
+const EXIT_FAILURE: u8 = 1;
+const EXIT_NETWORK: u8 = 2;
+const EXIT_LINKEDIN: u8 = 3;
+const EXIT_INPUT: u8 = 4;
+const EXIT_TIMEOUT: u8 = 5;
+// 128 + SIGINT, what shells report for a run ended with Ctrl-C.
+const EXIT_INTERRUPTED: i32 = 130;
+
+// Durations of the network phases for --timings, in the order they ran.
+static TIMINGS: Mutex<Vec<(&'static str, Duration)>> = Mutex::new(Vec::new());
+
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+const USER_AGENT: &str = concat!("linkedin-auth/", env!("CARGO_PKG_VERSION"));
+// Headers the requests set themselves, --header must not change them.
+const RESERVED_HEADERS: &[&str] = &["authorization", "content-type", "content-length", "host", "user-agent"];
+
+// LinkedIn authorization codes expire 30 minutes after they are issued. The
+// URL is shown before the member signs in, so a code pasted later than this
+// after the URL has expired for sure.
+const CODE_LIFETIME: Duration = Duration::from_secs(30 * 60);
+
+// Fields of the token response --field can print.
+const TOKEN_FIELDS: &[&str] = &["access_token", "token_type", "expires_in", "expires_at", "refresh_token",
+                                "refresh_token_expires_in", "scope", "id_token"];
+
+const EXIT_CODES_HELP: &str = "EXIT CODES:
+    0    Success
+    1    Any other failure
+    2    Network error while talking to LinkedIn
+    3    LinkedIn rejected the request, e.g. with an OAuth error
+    4    Invalid arguments, config file or pasted code
+    5    Gave up waiting for the code or the redirect";
 
 
 #[derive(Debug)]
-struct ValueError;
+struct MissingSecret;
 
-impl fmt::Display for ValueError {
+impl fmt::Display for MissingSecret {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "cannot retrieve access key value from the response")
+        write!(f, "client secret is not provided, pass --client-secret, set client_secret \
+                   in the [linkedin] table of the config file or LINKEDIN_CLIENT_SECRET")
     }
 }
 
-impl Error for ValueError {}
+impl Error for MissingSecret {}
 
 
-fn cli() -> ArgMatches<'static> {
-    App::new("LinkedIn Auth")
-        .version("0.0.1")
-        .author("Anton Zhyltsou")
-        .about("Automates the process of LinkedIn app authentication")
-        .arg(
-            Arg::with_name("client-id")
-            .short("c")
-            .long("client-id")
-            .help(
-                concat!(
-                    "Client ID of the application. Can be retrieved",
-                    "from the apps list in the LIN service account."
-                )
+#[derive(Debug)]
+struct MissingClientId;
+
+impl fmt::Display for MissingClientId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "client id is not provided, pass --client-id, set client_id \
+                   in the [linkedin] table of the config file or LINKEDIN_CLIENT_ID")
+    }
+}
+
+impl Error for MissingClientId {}
+
+
+#[derive(Debug)]
+struct InvalidProxy(ReqError);
+
+impl fmt::Display for InvalidProxy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid proxy URL: {}", self.0)
+    }
+}
+
+impl Error for InvalidProxy {}
+
+
+#[derive(Debug)]
+struct InsecureLinkedIn(String);
+
+impl fmt::Display for InsecureLinkedIn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "--danger-accept-invalid-certs is only meant for test servers and refused for {}", self.0)
+    }
+}
+
+impl Error for InsecureLinkedIn {}
+
+
+#[derive(Debug)]
+struct NoStoredToken;
+
+impl fmt::Display for NoStoredToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no access key is stored for this client id and permissions, \
+                   run the authorization first or pass --token-file")
+    }
+}
+
+impl Error for NoStoredToken {}
+
+
+#[derive(Debug)]
+struct StoredTokenExpired {
+    refreshable: bool,
+}
+
+impl fmt::Display for StoredTokenExpired {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.refreshable {
+            true => write!(f, "the stored access key has expired, run 'refresh' with its \
+                               refresh token or authorize again"),
+            false => write!(f, "the stored access key has expired, please authorize again"),
+        }
+    }
+}
+
+impl Error for StoredTokenExpired {}
+
+
+#[derive(Debug)]
+struct MalformedAuthParam(String);
+
+impl fmt::Display for MalformedAuthParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "--auth-param expects 'key=value', got '{}'", self.0)
+    }
+}
+
+impl Error for MalformedAuthParam {}
+
+
+#[derive(Debug)]
+struct InvalidClientId(String);
+
+impl fmt::Display for InvalidClientId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' does not look like a LinkedIn client id, which is at least 10 letters and digits. \
+                   Copy it from the Auth tab of the app at https://www.linkedin.com/developers/apps", self.0)
+    }
+}
+
+impl Error for InvalidClientId {}
+
+
+#[derive(Debug)]
+struct EmptyBatch;
+
+impl fmt::Display for EmptyBatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the config file has no [[batch]] entries")
+    }
+}
+
+impl Error for EmptyBatch {}
+
+
+#[derive(Debug)]
+struct ChecksFailed(usize);
+
+impl fmt::Display for ChecksFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            1 => write!(f, "1 check failed"),
+            count => write!(f, "{} checks failed", count),
+        }
+    }
+}
+
+impl Error for ChecksFailed {}
+
+
+#[derive(Debug)]
+struct MalformedHeader(String);
+
+impl fmt::Display for MalformedHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "--header expects 'name: value' with a valid header name and value, got '{}'", self.0)
+    }
+}
+
+impl Error for MalformedHeader {}
+
+
+#[derive(Debug)]
+struct ReservedHeader(String);
+
+impl fmt::Display for ReservedHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the {} header is set by the tool itself and cannot be passed with --header", self.0)
+    }
+}
+
+impl Error for ReservedHeader {}
+
+
+fn client_id_arg() -> Arg {
+    Arg::new("client-id")
+        .short('c')
+        .long("client-id")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "Client ID of the application. Can be retrieved",
+                "from the apps list in the LIN service account."
             )
-            .takes_value(true)
-            .required(true)
         )
-        .arg(
-            Arg::with_name("client-secret")
-            .short("s")
-            .long("client-secret")
-            .help(
-                concat!(
-                    "Client secret of the application. Can be retrieved",
-                    "from the apps list in the LIN service account."
-                )
+        .env("LINKEDIN_CLIENT_ID")
+        .hide_env_values(true)
+}
+
+
+fn client_secret_arg() -> Arg {
+    Arg::new("client-secret")
+        .short('s')
+        .long("client-secret")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "Client secret of the application. Can be retrieved",
+                "from the apps list in the LIN service account."
             )
-            .takes_value(true)
-            .required(true)
         )
-        .arg(
-            Arg::with_name("permissions")
-            .short("p")
-            .long("permissions")
-            .help("A list of permissions of the application.")
-            .multiple(true)
-            .takes_value(true)
-            .default_value("r_ads")
+        .env("LINKEDIN_CLIENT_SECRET")
+        .hide_env_values(true)
+}
+
+
+fn credentials_file_arg() -> Arg {
+    Arg::new("credentials-file")
+        .long("credentials-file")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "File with client_id and client_secret as TOML or KEY=VALUE lines, taking precedence ",
+                "over the config file. Refused unless only its owner can access it (chmod 600)."
+            )
         )
-        .arg(
-            Arg::with_name("redirect-url")
-            .short("r")
-            .long("redirect-url")
-            .help(
-                concat!(
-                    "Redirect URL in 'https://{url}' format to which the needed",
-                    "parameters for authentication",
-                    "will be provided as query params."
-                )
+}
+
+
+fn config_arg() -> Arg {
+    Arg::new("config")
+        .long("config")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "TOML file with a [linkedin] table of client_id, client_secret, redirect_url ",
+                "and scopes, './linkedin-auth.toml' when present by default. ",
+                "Flags override it, it overrides the environment."
+            )
+        )
+}
+
+
+fn profile_arg() -> Arg {
+    Arg::new("profile")
+        .long("profile")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "Name of the [profiles.<name>] table of the config file to use, ",
+                "'default' when present by default."
+            )
+        )
+}
+
+
+fn output_arg() -> Arg {
+    Arg::new("output")
+        .short('o')
+        .long("output")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "Write the retrieved token as JSON to the given file ",
+                "instead of printing it."
             )
-            .takes_value(true)
-            .default_value("https://localhost:8000")
         )
-        .get_matches()
 }
 
 
-fn generate_csrf() -> String {
-    let random_bytes: Vec<u8> = (0..256).map(|_| thread_rng().gen::<u8>()).collect();
-    encode_config(&random_bytes, base64::URL_SAFE_NO_PAD)
+fn timeout_arg() -> Arg {
+    Arg::new("timeout")
+        .long("timeout")
+        .action(ArgAction::Set)
+        .help("Seconds to wait for LinkedIn to respond to a request.")
+        .default_value("30")
+}
+
+
+fn pool_idle_timeout_arg() -> Arg {
+    Arg::new("pool-idle-timeout")
+        .long("pool-idle-timeout")
+        .action(ArgAction::Set)
+        .help("Seconds an idle connection to LinkedIn is kept open for reuse, 90 by default.")
+}
+
+
+fn no_keep_alive_arg() -> Arg {
+    Arg::new("no-keep-alive")
+        .long("no-keep-alive")
+        .action(ArgAction::SetTrue)
+        .help("Open a new connection for every request instead of reusing idle ones.")
+        .conflicts_with("pool-idle-timeout")
 }
 
 
-fn request_access_key(client_id: &str, client_secret: &str,
-                      auth_code: &str, redirect_url: &str, csrf: &str)
-                        -> Result<String, Box<dyn Error>> {
+fn user_agent_arg() -> Arg {
+    Arg::new("user-agent")
+        .long("user-agent")
+        .action(ArgAction::Set)
+        .help("User-Agent header sent with every request.")
+        .default_value(USER_AGENT)
+}
+
 
-    let response = Client::new()
-        .get(ACCESS_TOKEN_URL)
-        .query(
-            &[("response_type", "code"), ("client_id", client_id),
-              ("client_secret", client_secret), ("code", auth_code),
-              ("redirect_uri", redirect_url), ("state", csrf),
-              ("grant_type", "authorization_code")]
+fn header_arg() -> Arg {
+    Arg::new("header")
+        .long("header")
+        .action(ArgAction::Append)
+        .help(
+            concat!(
+                "Extra 'name: value' header sent with every request, can be repeated. ",
+                "Authorization, Content-Type, Host and User-Agent are rejected."
+            )
         )
-        .send()?;
+}
 
-    let data: Value = response.json().unwrap();
 
-    let data = match &data["access_token"] {
-        Value::String(key) => key,
-        _ => return Err(ValueError.into()),
-    };
-    Ok(data.clone())
+fn proxy_arg() -> Arg {
+    Arg::new("proxy")
+        .long("proxy")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "Send all requests through the given 'http://' or 'socks5://' proxy. ",
+                "Defaults to HTTPS_PROXY or ALL_PROXY from the environment."
+            )
+        )
 }
 
 
-fn generate_auth_code_url(client_id: &str, redirect_url: &str,
-                          permissions: &Vec<&str>, csrf: &str)
-                              -> Result<String, ReqError> {
+fn danger_accept_invalid_certs_arg() -> Arg {
+    Arg::new("danger-accept-invalid-certs")
+        .long("danger-accept-invalid-certs")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Do not verify TLS certificates, only for endpoint overrides pointing at test ",
+                "servers with self-signed certificates. Refused for linkedin.com hosts."
+            )
+        )
+}
+
 
-    let permissions_str = permissions.join(" ");
+fn auth_method_arg() -> Arg {
+    Arg::new("auth-method")
+        .long("auth-method")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "How the client credentials are sent to the token endpoint: 'body' as form ",
+                "fields, 'basic' as an 'Authorization: Basic' header."
+            )
+        )
+        .value_parser(["body", "basic"])
+        .default_value("body")
+}
 
-    let response = Client::new()
-        .get(AUTH_URL)
-        .query(
-            &[("response_type", "code"), ("client_id", client_id),
-              ("redirect_uri", redirect_url), ("state", csrf), 
-              ("scope", &permissions_str)]
-        );
 
-    let url = response.build()?.url().as_str().to_string();
-    Ok(url)
+fn max_callback_requests_arg() -> Arg {
+    Arg::new("max-callback-requests")
+        .long("max-callback-requests")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "How many requests other than the redirect the local server answers ",
+                "before giving up, e.g. ones fired by browser extensions."
+            )
+        )
+        .value_parser(|requests: &str| match requests.parse::<usize>() {
+            Ok(requests) if requests >= 1 => Ok(requests.to_string()),
+            _ => Err("expected a number of at least 1"),
+        })
+        .default_value("50")
 }
 
 
-fn controller(args: &ArgMatches) -> Result<(), Box<dyn Error>> {
-    let csrf = generate_csrf();
-    let client_id = args.value_of("client-id").unwrap();
-    let redirect_url = args.value_of("redirect-url").unwrap();
-    let permissions: Vec<&str> = args.values_of("permissions").unwrap().collect();
-    let client_secret = args.value_of("client-secret").unwrap();
+fn auth_url_arg() -> Arg {
+    Arg::new("auth-url")
+        .long("auth-url")
+        .action(ArgAction::Set)
+        .help("Authorization endpoint to use instead of LinkedIn's.")
+        .default_value(AUTH_URL)
+        .hide(true)
+}
 
-    let url = generate_auth_code_url(client_id, redirect_url,
-                                     &permissions, &csrf)?;
 
-    println!(
-        "\nGenerated URL to request the LIN authorization code for your application:\n\n\
-        {}\n\n\
-        Please, proceed with it and sign in with your account. \
-        After authorization, you'll be redirected to the page requested in CLI. \n\n\
-        Please, copy the 'code' value from the request parameters and pass it here:\n",
-        url
-    );
+fn token_url_arg() -> Arg {
+    Arg::new("token-url")
+        .long("token-url")
+        .action(ArgAction::Set)
+        .help("Token endpoint to use instead of LinkedIn's.")
+        .default_value(ACCESS_TOKEN_URL)
+        .hide(true)
+}
 
-    let mut authorization_code = String::new();
-    io::stdin().read_line(&mut authorization_code)?;
 
-    let access_key = request_access_key(client_id, client_secret,
-                                        &authorization_code, redirect_url, &csrf)?;
-    
-    println!("\nAccess key retrieved successfuly:\n\n{}.\n\nYou can now use it.",
-             access_key);
-    Ok(())
+fn revoke_url_arg() -> Arg {
+    Arg::new("revoke-url")
+        .long("revoke-url")
+        .action(ArgAction::Set)
+        .help("Revocation endpoint to use instead of LinkedIn's.")
+        .default_value(REVOKE_URL)
+        .hide(true)
+}
+
+
+fn api_url_arg() -> Arg {
+    Arg::new("api-url")
+        .long("api-url")
+        .action(ArgAction::Set)
+        .help("API base URL to use for --verify instead of LinkedIn's.")
+        .default_value(API_URL)
+        .hide(true)
+}
+
+
+fn cache_path_arg() -> Arg {
+    Arg::new("cache-path")
+        .long("cache-path")
+        .action(ArgAction::Set)
+        .help("Token cache location, '~/.config/linkedin-auth/tokens.json' by default.")
+}
+
+
+fn keyring_arg() -> Arg {
+    Arg::new("keyring")
+        .long("keyring")
+        .action(ArgAction::SetTrue)
+        .help("Keep the cached tokens in the OS keyring instead of a JSON file.")
+        .conflicts_with("cache-path")
+}
+
+
+fn max_retries_arg() -> Arg {
+    Arg::new("max-retries")
+        .long("max-retries")
+        .action(ArgAction::Set)
+        .help("How many times to repeat a token request after a network error, 429 or 5xx.")
+        .default_value("3")
+}
+
+
+fn retry_jitter_arg() -> Arg {
+    Arg::new("retry-jitter")
+        .long("retry-jitter")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Wait a random time up to the backoff before each retry, so scheduled runs hitting ",
+                "a 429 together do not retry together."
+            )
+        )
+}
+
+
+fn no_color_arg() -> Arg {
+    Arg::new("no-color")
+        .long("no-color")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Do not color the log output and error messages. Color is also left out when ",
+                "NO_COLOR is set or stderr is not a terminal."
+            )
+        )
+}
+
+
+fn verbose_arg() -> Arg {
+    Arg::new("verbose")
+        .short('v')
+        .long("verbose")
+        .action(ArgAction::Count)
+        .help(
+            concat!(
+                "Log the requests made to LinkedIn to stderr, ",
+                "repeat to also log request and response bodies."
+            )
+        )
+}
+
+
+fn quiet_arg() -> Arg {
+    Arg::new("quiet")
+        .short('q')
+        .long("quiet")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Print only the bare access key, without the instructions and the report. ",
+                "--json, --env and --format still take precedence."
+            )
+        )
 }
 
 
-fn main() {
-    let jira_auth = cli();
+fn json_arg() -> Arg {
+    Arg::new("json")
+        .long("json")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Print the full token response as a single JSON line ",
+                "and move the instructions to stderr."
+            )
+        )
+}
+
+
+fn dotenv_arg() -> Arg {
+    Arg::new("dotenv")
+        .long("dotenv")
+        .action(ArgAction::Set)
+        .help("Also set LINKEDIN_ACCESS_TOKEN and related keys in the given .env file.")
+}
+
+
+fn timings_arg() -> Arg {
+    Arg::new("timings")
+        .long("timings")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Print how long connecting to the token endpoint, the token request and the ",
+                "--verify requests took to stderr, as a 'timings' JSON object with --json."
+            )
+        )
+}
+
+
+fn show_token_arg() -> Arg {
+    Arg::new("show-token")
+        .long("show-token")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Print the whole access and refresh tokens in the report instead of only ",
+                "their first and last 4 characters."
+            )
+        )
+}
+
+
+fn pretty_arg() -> Arg {
+    Arg::new("pretty")
+        .long("pretty")
+        .action(ArgAction::SetTrue)
+        .help("Indent the --json output for reading, it stays a single line by default.")
+}
+
+
+fn env_arg() -> Arg {
+    Arg::new("env")
+        .long("env")
+        .action(ArgAction::SetTrue)
+        .help(
+            concat!(
+                "Print the token as shell 'export' statements for eval ",
+                "and move the instructions to stderr."
+            )
+        )
+        .conflicts_with("json")
+}
+
+
+fn format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "Print the token through a template such as 'Bearer {access_token}', ",
+                "with {access_token}, {refresh_token}, {expires_in} and {scope} placeholders."
+            )
+        )
+        .value_parser(|template: &str| {
+            check_template(template).map(|_| template.to_string()).map_err(|err| err.to_string())
+        })
+        .conflicts_with_all(["json", "env"])
+}
+
+
+fn field_arg() -> Arg {
+    Arg::new("field")
+        .long("field")
+        .action(ArgAction::Set)
+        .help("Print only this field of the token response, failing when LinkedIn did not return it.")
+        .value_parser(PossibleValuesParser::new(TOKEN_FIELDS))
+        .conflicts_with_all(["json", "env", "format"])
+}
+
+
+fn permissions_arg() -> Arg {
+    Arg::new("permissions")
+        .short('p')
+        .long("permissions")
+        .action(ArgAction::Append)
+        .num_args(1..)
+        .help("A list of permissions of the application, separated by spaces or commas.")
+        .default_value("r_ads")
+}
+
+
+fn scope_set_arg() -> Arg {
+    Arg::new("scope-set")
+        .long("scope-set")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "Preset merged with --permissions: 'ads' (r_ads, r_ads_reporting), ",
+                "'profile' (openid, profile, email) or 'social' (w_member_social, r_liteprofile, ",
+                "w_organization_social, r_organization_social)."
+            )
+        )
+        .value_parser(["ads", "profile", "social"])
+}
+
+
+fn permissions_file_arg() -> Arg {
+    Arg::new("permissions-file")
+        .long("permissions-file")
+        .action(ArgAction::Set)
+        .help(
+            concat!(
+                "File with a newline or comma separated list of permissions, ",
+                "merged with --permissions. Blank lines and '#' comments are skipped."
+            )
+        )
+}
+
+
+fn cli() -> Command {
+    Command::new("LinkedIn Auth")
+        .version("0.0.1")
+        .author("Anton Zhyltsou")
+        .about("Automates the process of LinkedIn app authentication")
+        .after_help(EXIT_CODES_HELP)
+        .subcommand_negates_reqs(true)
+        .arg(config_arg())
+        .arg(profile_arg())
+        .arg(client_id_arg())
+        .arg(client_secret_arg())
+        .arg(credentials_file_arg())
+        .arg(auth_method_arg())
+        .arg(permissions_arg())
+        .arg(scope_set_arg())
+        .arg(permissions_file_arg())
+        .arg(
+            Arg::new("allow-unknown-scopes")
+            .long("allow-unknown-scopes")
+            .action(ArgAction::SetTrue)
+            .help("Do not reject permissions missing from the list of known LinkedIn scopes.")
+        )
+        .arg(
+            Arg::new("app-products")
+            .long("app-products")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "File listing the products or permissions the app is provisioned for, ",
+                    "permissions outside of it are rejected before the URL is generated."
+                )
+            )
+        )
+        .arg(
+            Arg::new("strict")
+            .long("strict")
+            .action(ArgAction::SetTrue)
+            .help("Reject a client id that does not look like one instead of only warning about it.")
+        )
+        .arg(
+            Arg::new("interactive-scopes")
+            .long("interactive-scopes")
+            .action(ArgAction::SetTrue)
+            .help(
+                concat!(
+                    "When no permissions are given, pick them from a menu of known LinkedIn scopes. ",
+                    "Ignored when stdin is not a terminal."
+                )
+            )
+        )
+        .arg(
+            Arg::new("redirect-url")
+            .short('r')
+            .long("redirect-url")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "Redirect URL in 'https://{url}' format to which the needed",
+                    "parameters for authentication",
+                    "will be provided as query params."
+                )
+            )
+            .default_value("https://localhost:8000")
+        )
+        .arg(
+            Arg::new("no-server")
+            .long("no-server")
+            .action(ArgAction::SetTrue)
+            .help(
+                concat!(
+                    "Do not start a local server to capture the code for ",
+                    "'http(s)://localhost:{port}' redirect URLs, paste it manually instead."
+                )
+            )
+        )
+        .arg(
+            Arg::new("listen-addr")
+            .long("listen-addr")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "'ip:port' the local server listens on instead of the host and port of the ",
+                    "redirect URL, e.g. when a tunnel forwards the redirect."
+                )
+            )
+            .conflicts_with("no-server")
+        )
+        .arg(
+            Arg::new("callback-timeout")
+            .long("callback-timeout")
+            .action(ArgAction::Set)
+            .help("Seconds to wait for the redirect on the local server.")
+            .default_value("300")
+        )
+        .arg(max_callback_requests_arg())
+        .arg(
+            Arg::new("wait-timeout")
+            .long("wait-timeout")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "Seconds to wait for the code to be pasted, and for the redirect ",
+                    "when given. Zero waits forever."
+                )
+            )
+            .default_value("0")
+        )
+        .arg(
+            Arg::new("grant-type")
+            .long("grant-type")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "OAuth grant to use. 'client_credentials' requests an application ",
+                    "token directly, without signing in through the browser."
+                )
+            )
+            .value_parser(["authorization_code", "client_credentials"])
+            .default_value("authorization_code")
+        )
+        .arg(
+            Arg::new("pkce")
+            .long("pkce")
+            .action(ArgAction::SetTrue)
+            .help(
+                concat!(
+                    "Use PKCE: send an S256 code challenge with the authorization URL ",
+                    "and the code verifier instead of the client secret."
+                )
+            )
+        )
+        .arg(
+            Arg::new("pkce-verifier-length")
+            .long("pkce-verifier-length")
+            .action(ArgAction::Set)
+            .help("Length of the PKCE code verifier, from 43 to 128 characters.")
+            .default_value("64")
+        )
+        .arg(
+            Arg::new("prompt")
+            .long("prompt")
+            .action(ArgAction::Set)
+            .help("Adds 'prompt' to the authorization URL, e.g. 'consent' to approve the scopes again.")
+            .value_parser(PossibleValuesParser::new(PROMPT_VALUES))
+        )
+        .arg(
+            Arg::new("display")
+            .long("display")
+            .action(ArgAction::Set)
+            .help("Adds 'display' to the authorization URL, e.g. 'popup' for a smaller sign in page.")
+            .value_parser(PossibleValuesParser::new(DISPLAY_VALUES))
+        )
+        .arg(
+            Arg::new("auth-param")
+            .long("auth-param")
+            .action(ArgAction::Append)
+            .help(
+                concat!(
+                    "Extra 'key=value' parameter appended to the authorization URL, ",
+                    "can be repeated. Parameters set by the tool itself are rejected."
+                )
+            )
+        )
+        .arg(
+            Arg::new("code")
+            .long("code")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "Exchange this authorization code right away, without showing the URL ",
+                    "or waiting for the redirect."
+                )
+            )
+            .conflicts_with_all(["dry-run", "pkce"])
+        )
+        .arg(
+            Arg::new("code-file")
+            .long("code-file")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "Read the code (or the whole redirect URL) from a file or named pipe ",
+                    "written by another process instead of waiting for it here."
+                )
+            )
+            .conflicts_with_all(["code", "dry-run"])
+        )
+        .arg(
+            // Generated states are URL-safe base64, so they may start with '-'.
+            Arg::new("state")
+            .long("state")
+            .action(ArgAction::Set)
+            .allow_hyphen_values(true)
+            .help("The 'state' returned with --code, it must match the CSRF token of the URL.")
+            .requires("code")
+        )
+        .arg(
+            Arg::new("csrf")
+            .long("csrf")
+            .action(ArgAction::Set)
+            .allow_hyphen_values(true)
+            .help(
+                concat!(
+                    "Use this value as the 'state' of the authorization URL instead of a random ",
+                    "one, so a later run with --code can exchange the code it returned."
+                )
+            )
+        )
+        .arg(
+            Arg::new("session-file")
+            .long("session-file")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "Where the state (and PKCE verifier) of the generated URL is kept for a later ",
+                    "run with --code, '~/.config/linkedin-auth/session.json' by default."
+                )
+            )
+        )
+        .arg(
+            Arg::new("no-cache")
+            .long("no-cache")
+            .action(ArgAction::SetTrue)
+            .help("Always start a new authorization instead of reusing a cached token.")
+        )
+        .arg(cache_path_arg())
+        .arg(keyring_arg())
+        .arg(
+            Arg::new("dry-run")
+            .long("dry-run")
+            .action(ArgAction::SetTrue)
+            .help("Only print the authorization URL, without waiting for the code.")
+            .conflicts_with("env")
+        )
+        .arg(
+            Arg::new("offline")
+            .long("offline")
+            .action(ArgAction::SetTrue)
+            .help(
+                concat!(
+                    "A refresh token is needed: skip cached tokens without one and warn when ",
+                    "LinkedIn does not return one."
+                )
+            )
+        )
+        .arg(
+            Arg::new("print-curl")
+            .long("print-curl")
+            .action(ArgAction::SetTrue)
+            .help(
+                concat!(
+                    "Print the curl command of the token request to stderr, with the secret masked ",
+                    "unless --show-token is given. With --dry-run the code is a placeholder."
+                )
+            )
+        )
+        .arg(
+            Arg::new("open")
+            .long("open")
+            .action(ArgAction::SetTrue)
+            .help("Open the generated URL in the default browser.")
+        )
+        .arg(
+            Arg::new("qr")
+            .long("qr")
+            .action(ArgAction::SetTrue)
+            .help("Also print the generated URL as a QR code, to authorize from a phone.")
+        )
+        .arg(
+            Arg::new("copy")
+            .long("copy")
+            .action(ArgAction::SetTrue)
+            .help("Copy the generated URL to the clipboard, e.g. to open it on another machine.")
+        )
+        .arg(
+            Arg::new("verify")
+            .long("verify")
+            .action(ArgAction::SetTrue)
+            .help(
+                concat!(
+                    "Check the retrieved token by reading the signed in member ",
+                    "from LinkedIn's 'userinfo' or 'me' endpoint."
+                )
+            )
+            .conflicts_with("dry-run")
+        )
+        .arg(
+            Arg::new("organization")
+            .long("organization")
+            .action(ArgAction::Set)
+            .help(
+                concat!(
+                    "With --verify, also check that the member holds an approved role in this ",
+                    "organization, given as 'urn:li:organization:<id>' or the bare id."
+                )
+            )
+            .value_parser(|organization: &str| {
+                let id = organization.strip_prefix("urn:li:organization:").unwrap_or(organization);
+                match !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit()) {
+                    true => Ok(format!("urn:li:organization:{}", id)),
+                    false => Err("expected 'urn:li:organization:<id>' or a numeric id"),
+                }
+            })
+            .requires("verify")
+        )
+        .arg(timeout_arg())
+        .arg(max_retries_arg())
+        .arg(retry_jitter_arg())
+        .arg(pool_idle_timeout_arg())
+        .arg(no_keep_alive_arg())
+        .arg(proxy_arg())
+        .arg(danger_accept_invalid_certs_arg())
+        .arg(user_agent_arg())
+        .arg(header_arg())
+        .arg(auth_url_arg())
+        .arg(token_url_arg())
+        .arg(api_url_arg())
+        .arg(output_arg())
+        .arg(json_arg())
+        .arg(pretty_arg())
+        .arg(show_token_arg())
+        .arg(timings_arg())
+        .arg(env_arg())
+        .arg(format_arg())
+        .arg(field_arg())
+        .arg(dotenv_arg())
+        .arg(quiet_arg())
+        .arg(no_color_arg())
+        .arg(verbose_arg())
+        .subcommand(
+            Command::new("refresh")
+            .about("Exchanges a refresh token for a new access key")
+            .arg(config_arg())
+            .arg(profile_arg())
+            .arg(client_id_arg())
+            .arg(client_secret_arg())
+            .arg(credentials_file_arg())
+            .arg(auth_method_arg())
+            .arg(
+                Arg::new("refresh-token")
+                .long("refresh-token")
+                .action(ArgAction::Set)
+                .help("Refresh token previously issued for the application.")
+                .required(true)
+            )
+            .arg(timeout_arg())
+            .arg(max_retries_arg())
+            .arg(retry_jitter_arg())
+            .arg(pool_idle_timeout_arg())
+            .arg(no_keep_alive_arg())
+            .arg(proxy_arg())
+            .arg(danger_accept_invalid_certs_arg())
+            .arg(user_agent_arg())
+            .arg(header_arg())
+            .arg(token_url_arg())
+            .arg(output_arg())
+            .arg(json_arg())
+            .arg(pretty_arg())
+            .arg(show_token_arg())
+            .arg(timings_arg())
+            .arg(env_arg())
+            .arg(format_arg())
+            .arg(field_arg())
+            .arg(dotenv_arg())
+            .arg(quiet_arg())
+            .arg(no_color_arg())
+            .arg(verbose_arg())
+        )
+        .subcommand(
+            Command::new("revoke")
+            .about("Invalidates an access or refresh token")
+            .arg(config_arg())
+            .arg(profile_arg())
+            .arg(client_id_arg())
+            .arg(client_secret_arg())
+            .arg(credentials_file_arg())
+            .arg(auth_method_arg())
+            .arg(
+                Arg::new("token")
+                .long("token")
+                .action(ArgAction::Set)
+                .help("Access or refresh token to revoke.")
+                .required(true)
+            )
+            .arg(
+                Arg::new("no-cache")
+                .long("no-cache")
+                .action(ArgAction::SetTrue)
+                .help("Leave the token cache untouched.")
+            )
+            .arg(cache_path_arg())
+            .arg(keyring_arg())
+            .arg(timeout_arg())
+            .arg(max_retries_arg())
+            .arg(retry_jitter_arg())
+            .arg(pool_idle_timeout_arg())
+            .arg(no_keep_alive_arg())
+            .arg(proxy_arg())
+            .arg(danger_accept_invalid_certs_arg())
+            .arg(user_agent_arg())
+            .arg(header_arg())
+            .arg(revoke_url_arg())
+            .arg(quiet_arg())
+            .arg(no_color_arg())
+            .arg(verbose_arg())
+        )
+        .subcommand(
+            Command::new("whoami")
+            .about("Prints the member behind the stored access key")
+            .arg(config_arg())
+            .arg(profile_arg())
+            .arg(client_id_arg())
+            .arg(permissions_arg())
+            .arg(scope_set_arg())
+            .arg(permissions_file_arg())
+            .arg(
+                Arg::new("token-file")
+                .long("token-file")
+                .action(ArgAction::Set)
+                .help("Read the access key from a file written by --output instead of the cache.")
+            )
+            .arg(cache_path_arg())
+            .arg(keyring_arg())
+            .arg(timeout_arg())
+            .arg(max_retries_arg())
+            .arg(retry_jitter_arg())
+            .arg(pool_idle_timeout_arg())
+            .arg(no_keep_alive_arg())
+            .arg(proxy_arg())
+            .arg(danger_accept_invalid_certs_arg())
+            .arg(user_agent_arg())
+            .arg(header_arg())
+            .arg(api_url_arg())
+            .arg(no_color_arg())
+            .arg(verbose_arg())
+        )
+        .subcommand(
+            Command::new("inspect")
+            .about("Decodes an id_token or checks whether an access key still works")
+            .arg(
+                Arg::new("token")
+                .long("token")
+                .action(ArgAction::Set)
+                .help("Access key or id_token to inspect.")
+                .required(true)
+            )
+            .arg(
+                Arg::new("offline")
+                .long("offline")
+                .action(ArgAction::SetTrue)
+                .help("Do not ask LinkedIn, only decode the token when it is a JWT.")
+            )
+            .arg(timeout_arg())
+            .arg(max_retries_arg())
+            .arg(retry_jitter_arg())
+            .arg(proxy_arg())
+            .arg(danger_accept_invalid_certs_arg())
+            .arg(user_agent_arg())
+            .arg(header_arg())
+            .arg(api_url_arg())
+            .arg(no_color_arg())
+            .arg(verbose_arg())
+        )
+        .subcommand(
+            Command::new("doctor")
+            .about("Checks the connection to LinkedIn, the client id, the redirect URL and the credentials")
+            .arg(config_arg())
+            .arg(profile_arg())
+            .arg(client_id_arg())
+            .arg(client_secret_arg())
+            .arg(credentials_file_arg())
+            .arg(auth_method_arg())
+            .arg(
+                Arg::new("redirect-url")
+                .short('r')
+                .long("redirect-url")
+                .action(ArgAction::Set)
+                .help("Redirect URL of the application to check.")
+                .default_value("https://localhost:8000")
+            )
+            .arg(timeout_arg())
+            .arg(max_retries_arg())
+            .arg(retry_jitter_arg())
+            .arg(proxy_arg())
+            .arg(danger_accept_invalid_certs_arg())
+            .arg(user_agent_arg())
+            .arg(header_arg())
+            .arg(auth_url_arg())
+            .arg(token_url_arg())
+            .arg(no_color_arg())
+            .arg(verbose_arg())
+        )
+        .subcommand(
+            Command::new("batch")
+            .about("Requests one token per [[batch]] entry of the config file and prints a summary")
+            .arg(config_arg().required(true))
+            .arg(profile_arg())
+            .arg(client_id_arg())
+            .arg(client_secret_arg())
+            .arg(credentials_file_arg())
+            .arg(auth_method_arg())
+            .arg(
+                Arg::new("redirect-url")
+                .short('r')
+                .long("redirect-url")
+                .action(ArgAction::Set)
+                .help("Redirect URL of the application, used for every entry.")
+                .default_value("https://localhost:8000")
+            )
+            .arg(
+                Arg::new("no-server")
+                .long("no-server")
+                .action(ArgAction::SetTrue)
+                .help("Paste each code instead of capturing it with a local server.")
+            )
+            .arg(max_callback_requests_arg().conflicts_with("no-server"))
+            .arg(
+                Arg::new("open")
+                .long("open")
+                .action(ArgAction::SetTrue)
+                .help("Open each authorization URL in the default browser.")
+            )
+            .arg(
+                Arg::new("output-dir")
+                .long("output-dir")
+                .action(ArgAction::Set)
+                .help("Directory the token files of the entries are written to.")
+                .default_value(".")
+            )
+            .arg(show_token_arg())
+            .arg(timeout_arg())
+            .arg(max_retries_arg())
+            .arg(retry_jitter_arg())
+            .arg(proxy_arg())
+            .arg(danger_accept_invalid_certs_arg())
+            .arg(user_agent_arg())
+            .arg(header_arg())
+            .arg(auth_url_arg())
+            .arg(token_url_arg())
+            .arg(quiet_arg())
+            .arg(no_color_arg())
+            .arg(verbose_arg())
+        )
+        .subcommand(
+            Command::new("completions")
+            .about("Prints a shell completion script to stdout")
+            .hide(true)
+            .arg(
+                Arg::new("shell")
+                .help("Shell to generate the completion script for.")
+                .value_parser(["bash", "zsh", "fish", "powershell"])
+                .required(true)
+            )
+        )
+}
+
+
+// Subcommands share the helpers reading the flags but define fewer of them,
+// a flag a command does not define reads as absent.
+fn value<'a>(args: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    args.try_get_one::<String>(name).ok().flatten().map(String::as_str)
+}
+
+
+fn values<'a>(args: &'a ArgMatches, name: &str) -> impl Iterator<Item = &'a str> {
+    args.try_get_many::<String>(name).ok().flatten().into_iter().flatten().map(String::as_str)
+}
+
+
+fn flag(args: &ArgMatches, name: &str) -> bool {
+    args.try_get_one::<bool>(name).ok().flatten().copied().unwrap_or_default()
+}
+
+
+// Whether `name` was passed on the command line, rather than taken from the
+// environment or its default.
+fn on_command_line(args: &ArgMatches, name: &str) -> bool {
+    args.value_source(name) == Some(ValueSource::CommandLine)
+}
+
+
+// An explicit --config or --profile needs the file to exist, otherwise the
+// default file is only read when present.
+fn load_config(args: &ArgMatches) -> Result<Config, Box<dyn Error>> {
+    let default_path = Path::new(DEFAULT_CONFIG_PATH);
+    let profile = value(args, "profile");
+    let mut config = match value(args, "config") {
+        Some(path) => Config::load(Path::new(path), profile)?,
+        None if default_path.is_file() || profile.is_some() => Config::load(default_path, profile)?,
+        None => Config::default(),
+    };
+
+    // The credentials file wins over the config file, flags over both.
+    if let Some(path) = value(args, "credentials-file") {
+        let credentials = Credentials::load(Path::new(path))?;
+        config.client_id = credentials.client_id.or(config.client_id);
+        config.client_secret = credentials.client_secret.or(config.client_secret);
+    }
+    Ok(config)
+}
+
+
+// A flag given on the command line wins over the config file, which wins
+// over the environment variable and the built-in default of the flag.
+fn setting<'a>(args: &'a ArgMatches, name: &str, config: &'a Option<String>) -> Option<&'a str> {
+    match on_command_line(args, name) {
+        false => config.as_deref().or_else(|| value(args, name)),
+        true => value(args, name),
+    }
+}
+
+
+fn client_id<'a>(args: &'a ArgMatches, config: &'a Config) -> Result<&'a str, MissingClientId> {
+    setting(args, "client-id", &config.client_id).map(str::trim).ok_or(MissingClientId)
+}
+
+
+// Lenient on purpose, LinkedIn may change the format: anything of 10 or more
+// letters and digits passes. Shared by the main flow, where only --strict
+// turns a mismatch into an error, and `doctor`.
+fn validate_client_id(client_id: &str) -> Result<(), InvalidClientId> {
+    match client_id.len() >= 10 && client_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        true => Ok(()),
+        false => Err(InvalidClientId(client_id.to_string())),
+    }
+}
+
+
+// The built-in default only applies when neither flags, the config file, a
+// scope set nor a permissions file name any scope.
+fn permissions(args: &ArgMatches, config: &Config) -> Result<Vec<String>, ScopesFileError> {
+    let mut permissions: Vec<String> = value(args, "scope-set")
+        .and_then(scope_set)
+        .unwrap_or_default()
+        .iter()
+        .map(|scope| scope.to_string())
+        .collect();
+
+    let other_source = value(args, "scope-set").is_some() || value(args, "permissions-file").is_some();
+    match (on_command_line(args, "permissions"), &config.scopes) {
+        (false, Some(scopes)) => permissions.extend(scopes.iter().cloned()),
+        (false, None) if other_source => {},
+        _ => permissions.extend(split_scopes(values(args, "permissions"))),
+    }
+    if let Some(path) = value(args, "permissions-file") {
+        permissions.extend(load_scopes_file(Path::new(path))?);
+    }
+
+    normalize_scopes(&mut permissions);
+    Ok(permissions)
+}
+
+
+// Only offered when nothing else names a scope, the menu starts from the
+// built-in default and an empty selection keeps it.
+fn pick_scopes(args: &ArgMatches, config: &Config,
+               permissions: Vec<String>) -> Result<Vec<String>, Box<dyn Error>> {
+    let other_source = on_command_line(args, "permissions") || config.scopes.is_some()
+        || value(args, "scope-set").is_some() || value(args, "permissions-file").is_some();
+    if !flag(args, "interactive-scopes") || other_source || !io::stdin().is_terminal() {
+        return Ok(permissions);
+    }
+
+    let choices: Vec<(&str, &str)> = SCOPE_CATEGORIES.iter()
+        .flat_map(|(category, scopes)| scopes.iter().map(move |scope| (*category, *scope)))
+        .collect();
+    let width = SCOPE_CATEGORIES.iter().map(|(category, _)| category.len()).max().unwrap_or_default();
+    let items: Vec<String> = choices.iter()
+        .map(|(category, scope)| format!("{:width$}  {}", category, scope, width = width))
+        .collect();
+    let defaults: Vec<bool> = choices.iter().map(|(_, scope)| permissions.iter().any(|p| p == scope)).collect();
+
+    let picked = MultiSelect::new()
+        .with_prompt("Permissions (space to toggle, enter to confirm)")
+        .items(&items)
+        .defaults(&defaults)
+        .interact()?;
+
+    match picked.is_empty() {
+        true => Ok(permissions),
+        false => Ok(picked.into_iter().map(|index| choices[index].1.to_string()).collect()),
+    }
+}
+
+
+fn client_secret(args: &ArgMatches, config: &Config) -> Result<String, Box<dyn Error>> {
+    if let Some(secret) = setting(args, "client-secret", &config.client_secret) {
+        return Ok(secret.to_string());
+    }
+
+    let secret = match io::stdin().is_terminal() {
+        true => rpassword::prompt_password("Client secret: ")?,
+        false => {
+            let mut secret = String::new();
+            io::stdin().read_line(&mut secret)?;
+            secret
+        },
+    };
+
+    match secret.trim() {
+        "" => Err(MissingSecret.into()),
+        secret => Ok(secret.to_string()),
+    }
+}
+
+
+// The output mode the flags ask for. Subcommands lacking some of the flags
+// get the defaults.
+fn reporter_for(args: &ArgMatches) -> Reporter {
+    let mode = if flag(args, "json") {
+        Mode::Json
+    } else if flag(args, "env") {
+        Mode::Env
+    } else if let Some(template) = value(args, "format") {
+        Mode::Template(template.to_string())
+    } else if let Some(name) = value(args, "field") {
+        Mode::Field(name.to_string())
+    } else if value(args, "output").is_some() {
+        Mode::Silent
+    } else {
+        Mode::Human
+    };
+    Reporter {
+        mode,
+        quiet: flag(args, "quiet"),
+        pretty: flag(args, "pretty"),
+        show_token: flag(args, "show-token"),
+    }
+}
+
+
+fn write_token_file(path: &str, token: &TokenResponse) -> Result<(), Box<dyn Error>> {
+    write_private_file(Path::new(path), &serde_json::to_vec(token)?)?;
+    Ok(())
+}
+
+
+fn session_path(args: &ArgMatches) -> Option<PathBuf> {
+    value(args, "session-file").map(PathBuf::from).or_else(Session::default_path)
+}
+
+
+// The session of the run that generated the URL, unless it was generated for
+// another application. Like the cache, a broken file is only reported.
+fn load_session(args: &ArgMatches, reporter: &Reporter, client_id: &str) -> Option<Session> {
+    match Session::load(&session_path(args)?) {
+        Ok(session) => session.filter(|session| session.client_id == client_id),
+        Err(err) => {
+            reporter.warn(&format!("cannot read the session file: {}", err));
+            None
+        },
+    }
+}
+
+
+fn save_session(args: &ArgMatches, reporter: &Reporter, client: &LinkedInAuthClient) {
+    if let Some(path) = session_path(args) {
+        if let Err(err) = client.session().save(&path) {
+            reporter.warn(&format!("cannot save the session file: {}", err));
+        }
+    }
+}
+
+
+// A broken cache only costs a fresh authorization, so it is reported
+// instead of failing the run.
+fn token_cache(args: &ArgMatches, reporter: &Reporter, client_id: &str) -> Option<TokenCache> {
+    if flag(args, "no-cache") {
+        return None;
+    }
+
+    let cache = match flag(args, "keyring") {
+        true => TokenCache::load_keyring(client_id),
+        false => {
+            let path = value(args, "cache-path").map(PathBuf::from)
+                .or_else(TokenCache::default_path)?;
+            TokenCache::load(&path)
+        },
+    };
+    match cache {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            reporter.warn(&format!("cannot read the token cache: {}", err));
+            None
+        },
+    }
+}
+
+
+fn warn_ignored_pretty(args: &ArgMatches, reporter: &Reporter) {
+    if flag(args, "pretty") && !flag(args, "json") {
+        reporter.warn("--pretty only applies to --json output and is ignored");
+    }
+}
+
+
+fn report_token(args: &ArgMatches, reporter: &Reporter, token: &TokenResponse) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = value(args, "output") {
+        write_token_file(path, token)?;
+        reporter.say(&format!("\nAccess key saved to {}.", path));
+    }
+    if let Some(path) = value(args, "dotenv") {
+        write_dotenv(Path::new(path), &token_variables(token))?;
+        reporter.say(&format!("\nAccess key written to {}.", path));
+    }
+    reporter.report_token(token)
+}
+
+
+// API calls send the token as `Authorization: Bearer`, which silently fails
+// for any other kind of token.
+fn warn_token_type(reporter: &Reporter, token: &TokenResponse) {
+    match &token.token_type {
+        _ if token.is_bearer() => {},
+        Some(token_type) => {
+            reporter.warn(&format!("LinkedIn returned a '{}' token instead of a bearer token", token_type));
+        },
+        None => reporter.warn("the token response has no token_type, API calls may fail"),
+    }
+}
+
+
+// LinkedIn has no scope or parameter asking for a refresh token, it only
+// issues them to apps it approved for programmatic refresh.
+fn warn_missing_refresh_token(reporter: &Reporter, token: &TokenResponse) {
+    if token.refresh_token.is_none() {
+        reporter.warn("--offline was given but LinkedIn returned no refresh token. LinkedIn only \
+                       issues refresh tokens to apps approved for programmatic refresh, check the Auth tab \
+                       of the app at https://www.linkedin.com/developers/apps or ask LinkedIn to enable it. \
+                       Until then a new authorization is needed when the access key expires");
+    }
+}
+
+
+fn warn_missing_scopes(reporter: &Reporter, token: &TokenResponse, requested: &[String]) {
+    if let Some(granted) = &token.scope {
+        let missing = missing_scopes(requested, granted);
+        if !missing.is_empty() {
+            reporter.warn(&format!("LinkedIn did not grant {}, check that the app is approved for them",
+                                   missing.join(", ")));
+        }
+    }
+}
+
+
+fn extra_headers(args: &ArgMatches) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    for header in values(args, "header") {
+        let malformed = || MalformedHeader(header.to_string());
+        let (name, value) = header.split_once(':').ok_or_else(malformed)?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| malformed())?;
+        if RESERVED_HEADERS.contains(&name.as_str()) {
+            return Err(ReservedHeader(name.to_string()).into());
+        }
+        headers.append(name, HeaderValue::from_str(value.trim()).map_err(|_| malformed())?);
+    }
+    Ok(headers)
+}
+
+
+// The endpoints this run sends requests to: the token and revocation
+// endpoints, and the API with --verify and whoami.
+fn requested_urls(args: &ArgMatches) -> Vec<&str> {
+    let mut urls = Vec::new();
+    urls.extend(value(args, "token-url"));
+    urls.extend(value(args, "revoke-url"));
+    if flag(args, "verify") || args.try_contains_id("verify").is_err() {
+        urls.extend(value(args, "api-url"));
+    }
+    urls
+}
+
+
+// The single HTTP client shared by every request of the invocation.
+fn http_client(args: &ArgMatches, reporter: &Reporter) -> Result<Client, Box<dyn Error>> {
+    let timeout: u64 = value(args, "timeout").unwrap().parse()?;
+
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(timeout))
+        .user_agent(value(args, "user-agent").unwrap_or(USER_AGENT))
+        .default_headers(extra_headers(args)?)
+        // Proxies may compress the answers even when LinkedIn does not.
+        .gzip(true)
+        .deflate(true)
+        .brotli(true);
+    if let Some(seconds) = value(args, "pool-idle-timeout") {
+        builder = builder.pool_idle_timeout(Duration::from_secs(seconds.parse()?));
+    }
+    // Without idle connections in the pool every request opens a new one.
+    if flag(args, "no-keep-alive") {
+        builder = builder.pool_max_idle_per_host(0);
+    }
+
+    // reqwest already honors HTTPS_PROXY by itself, ALL_PROXY only applies
+    // when neither the flag nor HTTPS_PROXY is given.
+    let proxy = match value(args, "proxy") {
+        Some(proxy) => Some(proxy.to_string()),
+        None if env::var_os("HTTPS_PROXY").or_else(|| env::var_os("https_proxy")).is_some() => None,
+        None => env::var("ALL_PROXY").or_else(|_| env::var("all_proxy")).ok(),
+    };
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::all(&proxy).map_err(InvalidProxy)?);
+    }
+
+    if flag(args, "danger-accept-invalid-certs") {
+        for url in requested_urls(args) {
+            if let Some(host) = Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)) {
+                if host == "linkedin.com" || host.ends_with(".linkedin.com") {
+                    return Err(InsecureLinkedIn(host).into());
+                }
+            }
+        }
+        reporter.warn("TLS certificates are NOT verified (--danger-accept-invalid-certs), \
+                             never use this outside of testing");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    Ok(builder.build()?)
+}
+
+
+#[cfg(feature = "qr")]
+fn print_qr(reporter: &Reporter, url: &str) {
+    use qrcode::QrCode;
+    use qrcode::render::unicode::Dense1x2;
+
+    match QrCode::new(url) {
+        Ok(code) => reporter.say(&format!("{}\n", code.render::<Dense1x2>().quiet_zone(true).build())),
+        Err(err) => reporter.warn(&format!("cannot render the URL as a QR code: {}", err)),
+    }
+}
+
+
+#[cfg(not(feature = "qr"))]
+fn print_qr(reporter: &Reporter, _url: &str) {
+    reporter.warn("--qr is not available, the tool was built without the 'qr' feature");
+}
+
+
+fn copy_to_clipboard(reporter: &Reporter, url: &str) -> Option<Clipboard> {
+    let result = Clipboard::new().and_then(|mut clipboard| {
+        clipboard.set_text(url)?;
+        Ok(clipboard)
+    });
+    match result {
+        Ok(clipboard) => {
+            reporter.say("The URL has been copied to the clipboard.\n");
+            Some(clipboard)
+        },
+        Err(err) => {
+            reporter.warn(&format!("cannot copy the URL to the clipboard: {}", err));
+            None
+        },
+    }
+}
+
+
+// The subcommands lack some of the endpoint flags, those fall back to
+// LinkedIn's own endpoints.
+fn auth_client(args: &ArgMatches, reporter: &Reporter, client_id: &str, client_secret: &str,
+               redirect_url: &str, permissions: &[&str])
+                   -> Result<LinkedInAuthClient, Box<dyn Error>> {
+    let client = LinkedInAuthClient::new(client_id, client_secret, redirect_url, permissions)
+        .with_http_client(http_client(args, reporter)?)
+        .with_max_retries(value(args, "max-retries").unwrap().parse()?)
+        .with_retry_jitter(flag(args, "retry-jitter"))
+        .with_auth_method(match value(args, "auth-method") {
+            Some("basic") => AuthMethod::Basic,
+            _ => AuthMethod::Body,
+        })
+        .with_endpoints(value(args, "auth-url").unwrap_or(AUTH_URL),
+                        value(args, "token-url").unwrap_or(ACCESS_TOKEN_URL))?
+        .with_revoke_url(value(args, "revoke-url").unwrap_or(REVOKE_URL))?
+        .with_api_url(value(args, "api-url").unwrap_or(API_URL))?;
+    Ok(client)
+}
+
+
+// Shows a spinner on stderr while `request` is in flight, only when both
+// streams are a terminal and the output is not meant to be minimal. How long
+// the request took is recorded under `phase` for --timings.
+async fn with_spinner<F: Future>(args: &ArgMatches, phase: &'static str, message: &str,
+                                 request: F) -> F::Output {
+    let visible = io::stdout().is_terminal() && io::stderr().is_terminal()
+        && !flag(args, "quiet") && !flag(args, "json");
+    let started = Instant::now();
+    let output = match visible {
+        true => {
+            let spinner = ProgressBar::new_spinner().with_message(message.to_string());
+            spinner.enable_steady_tick(SPINNER_TICK);
+            let output = request.await;
+            spinner.finish_and_clear();
+            output
+        },
+        false => request.await,
+    };
+    record_timing(phase, started.elapsed());
+    output
+}
+
+
+fn record_timing(phase: &'static str, duration: Duration) {
+    if let Ok(mut timings) = TIMINGS.lock() {
+        timings.push((phase, duration));
+    }
+}
+
+
+// reqwest does not expose its DNS and connect times, so with --timings a
+// separate connection to the token endpoint is timed before the request.
+fn time_connect(args: &ArgMatches) {
+    if !flag(args, "timings") {
+        return;
+    }
+    let timeout = value(args, "timeout").and_then(|timeout| timeout.parse().ok()).unwrap_or_default();
+    let token_url = value(args, "token-url").unwrap_or(ACCESS_TOKEN_URL);
+    let started = Instant::now();
+    if check_connection(token_url, Duration::from_secs(timeout)).is_ok() {
+        record_timing("connect", started.elapsed());
+    }
+}
+
+
+fn print_timings(args: &ArgMatches, reporter: &Reporter) {
+    if !flag(args, "timings") {
+        return;
+    }
+    let timings = match TIMINGS.lock() {
+        Ok(timings) => timings,
+        Err(_) => return,
+    };
+
+    match reporter.mode {
+        Mode::Json => {
+            let phases: serde_json::Map<String, serde_json::Value> = timings.iter()
+                .map(|(phase, duration)| (phase.to_string(), json!(duration.as_millis() as u64)))
+                .collect();
+            reporter.note(&json!({ "timings": phases }).to_string());
+        },
+        _ => {
+            reporter.note("\nTimings:");
+            for (phase, duration) in timings.iter() {
+                reporter.note(&format!("    {:<14}{:>6} ms", phase, duration.as_millis()));
+            }
+        },
+    }
+}
+
+
+async fn verify_token(args: &ArgMatches, reporter: &Reporter, client: &LinkedInAuthClient,
+                      token: &TokenResponse) -> Result<(), Box<dyn Error>> {
+    let request = client.fetch_member(&token.access_token);
+    let member = with_spinner(args, "verify", "Verifying the token...", request).await?;
+    reporter.say(&format!("\nToken verified, issued to {} ({}).", member.name, member.id));
+
+    // Member calls working while organization calls fail is the usual
+    // surprise with company pages, so the two are reported apart.
+    if let Some(organization) = value(args, "organization") {
+        let request = client.check_organization(&token.access_token, organization);
+        if let Err(err) = with_spinner(args, "organization", "Checking the organization...", request).await {
+            reporter.warn(&format!("the token works for member calls but not for {}", organization));
+            return Err(err.into());
+        }
+        reporter.say(&format!("The member can act for {}.", organization));
+    }
+    Ok(())
+}
+
+
+// Color only goes to a terminal, and not with --no-color or a non-empty
+// NO_COLOR (https://no-color.org).
+fn use_color(args: &ArgMatches) -> bool {
+    !flag(args, "no-color")
+        && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && io::stderr().is_terminal()
+}
+
+
+// Secrets are redacted by the library itself, so the log only has to be
+// limited to its own events.
+fn init_logging(args: &ArgMatches) {
+    let level = match args.get_count("verbose") {
+        0 => return,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(io::stderr).with_ansi(use_color(args)))
+        .with(Targets::new().with_target("lin_auth", level))
+        .init();
+}
+
+
+async fn controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    warn_ignored_pretty(args, reporter);
+    if value(args, "grant-type") == Some("client_credentials") {
+        return client_credentials_controller(args, reporter).await;
+    }
+
+    let config = load_config(args)?;
+    let client_id = client_id(args, &config)?;
+    if let Err(err) = validate_client_id(client_id) {
+        match flag(args, "strict") {
+            true => return Err(err.into()),
+            false => reporter.warn(&err.to_string()),
+        }
+    }
+    let redirect_url = setting(args, "redirect-url", &config.redirect_url).unwrap();
+    validate_redirect_url(redirect_url)?;
+    let permissions = pick_scopes(args, &config, permissions(args, &config)?)?;
+    let permissions: Vec<&str> = permissions.iter().map(String::as_str).collect();
+    if !flag(args, "allow-unknown-scopes") {
+        validate_scopes(&permissions)?;
+    }
+    if let Some(path) = value(args, "app-products") {
+        validate_provisioned_scopes(&permissions, &load_app_products(Path::new(path))?)?;
+    }
+
+    let dry_run = flag(args, "dry-run");
+    let mut cache = match dry_run {
+        true => None,
+        false => token_cache(args, reporter, client_id),
+    };
+    let key = cache_key(config.profile.as_deref(), client_id, &permissions);
+    let cached = match value(args, "code").is_some() {
+        true => None,
+        false => cache.as_ref().and_then(|cache| cache.get(&key)),
+    }.filter(|token| !flag(args, "offline") || token.refresh_token.is_some());
+    if let Some(token) = cached {
+        reporter.say("\nUsing the cached access key, pass --no-cache to request a new one.");
+        report_token(args, reporter, &token)?;
+        if flag(args, "verify") {
+            let client = auth_client(args, reporter, client_id, "", redirect_url, &permissions)?;
+            verify_token(args, reporter, &client, &token).await?;
+        }
+        return Ok(());
+    }
+
+    // A code from the URL of an earlier run needs that run's state and
+    // verifier, a stored verifier also spares the client secret.
+    let session = value(args, "code").and_then(|_| load_session(args, reporter, client_id));
+    let pkce = flag(args, "pkce") || session.as_ref().is_some_and(|session| session.code_verifier.is_some());
+    // A dry run never sends the secret, it is only shown by --print-curl.
+    let client_secret = match (pkce, dry_run) {
+        (true, _) => String::new(),
+        (false, true) => {
+            setting(args, "client-secret", &config.client_secret).unwrap_or("<client_secret>").to_string()
+        },
+        (false, false) => client_secret(args, &config)?,
+    };
+    // Zero waits forever, an explicit --wait-timeout also covers the server.
+    let wait_timeout = match value(args, "wait-timeout").unwrap().parse()? {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    };
+    let callback_timeout = match on_command_line(args, "wait-timeout") {
+        false => Some(Duration::from_secs(value(args, "callback-timeout").unwrap().parse()?)),
+        true => wait_timeout,
+    };
+
+    let no_server = flag(args, "no-server") || value(args, "code-file").is_some();
+    let callback = match (no_server, value(args, "listen-addr")) {
+        (true, _) => None,
+        (false, Some(listen_addr)) => Some(listen_address(redirect_url, listen_addr)?),
+        (false, None) => callback_address(redirect_url),
+    };
+
+    let mut client = auth_client(args, reporter, client_id, &client_secret, redirect_url, &permissions)?;
+    if pkce {
+        let verifier_length = value(args, "pkce-verifier-length").unwrap().parse()?;
+        client = client.with_pkce(verifier_length)?;
+    }
+    if let Some(prompt) = value(args, "prompt") {
+        client = client.with_prompt(prompt)?;
+    }
+    if let Some(display) = value(args, "display") {
+        client = client.with_display(display)?;
+    }
+    for param in values(args, "auth-param") {
+        let (name, value) = param.split_once('=').ok_or_else(|| MalformedAuthParam(param.to_string()))?;
+        client = client.with_auth_param(name, value)?;
+    }
+    if let Some(session) = &session {
+        client = client.with_session(session);
+    }
+    if let Some(csrf) = value(args, "csrf") {
+        client = client.with_csrf(csrf);
+    }
+
+    // A code obtained elsewhere is exchanged without any prompt.
+    if let Some(code) = value(args, "code") {
+        let state = returned_state(reporter, &client, value(args, "state").map(str::to_string));
+        return finish_authorization(args, reporter, &client, cache.as_mut(), &key, code, &state).await;
+    }
+    let url = client.authorization_url();
+    let generated_at = Instant::now();
+    save_session(args, reporter, &client);
+
+    if dry_run {
+        reporter.report_auth_url(&url, true)?;
+        print_curl(args, reporter, &client, "<code>");
+        return Ok(());
+    }
+
+    // Bound before the URL is shown, so a taken port cannot cost the code.
+    let max_requests = value(args, "max-callback-requests").unwrap().parse()?;
+    let server = callback
+        .map(|address| CallbackServer::bind(address).map(|server| server.with_max_requests(max_requests)))
+        .transpose()?;
+
+    reporter.report_auth_url(&url, false)?;
+
+    if flag(args, "open") {
+        if let Err(err) = webbrowser::open(&url) {
+            reporter.warn(&format!("cannot open the browser, please open the URL manually: {}", err));
+        }
+    }
+    if flag(args, "qr") {
+        print_qr(reporter, &url);
+    }
+    // On X11 and Wayland the URL is only served while the clipboard lives,
+    // so it is kept until the code arrives.
+    let _clipboard = match flag(args, "copy") {
+        true => copy_to_clipboard(reporter, &url),
+        false => None,
+    };
+
+    exit_on_interrupt(reporter);
+    let (authorization_code, state) = match (value(args, "code-file"), callback.zip(server)) {
+        (Some(path), _) => {
+            reporter.say(&format!("Waiting for the code to be written to {}...", path));
+            let pasted = read_code_file(Path::new(path))?;
+            warn_redirect_mismatch(reporter, redirect_url, &pasted);
+            (pasted.code, returned_state(reporter, &client, pasted.state))
+        },
+        (None, Some((address, server))) => {
+            if address.tls {
+                reporter.say("The local server uses a self-signed certificate, the browser will \
+                                   show a certificate warning that has to be accepted.");
+            }
+            let address = SocketAddr::from((address.ip, address.port));
+            reporter.say(&format!("Waiting for the redirect on {}...", address));
+            (server.wait(client.csrf(), callback_timeout)?, client.csrf().to_string())
+        },
+        (None, None) => {
+            reporter.say("Please, copy the 'code' value from the request parameters \
+                               (or the whole redirect URL) and pass it here:\n");
+            let reprompt = || reporter.say("The code is empty, please paste it again:\n");
+            let pasted = match wait_timeout {
+                Some(timeout) => read_stdin_code(timeout, reprompt)?,
+                None => read_code(&mut io::stdin().lock(), reprompt)?,
+            };
+            if generated_at.elapsed() > CODE_LIFETIME {
+                reporter.warn(&format!("more than {} minutes passed since the URL was generated, \
+                                        the code may have expired", CODE_LIFETIME.as_secs() / 60));
+            }
+            warn_redirect_mismatch(reporter, redirect_url, &pasted);
+            (pasted.code, returned_state(reporter, &client, pasted.state))
+        },
+    };
+
+    finish_authorization(args, reporter, &client, cache.as_mut(), &key, &authorization_code, &state).await
+}
+
+
+// Without the state LinkedIn sent back there is nothing to compare the CSRF
+// token with, so the check passes and the user is told it was skipped.
+fn returned_state(reporter: &Reporter, client: &LinkedInAuthClient, state: Option<String>) -> String {
+    state.unwrap_or_else(|| {
+        reporter.warn("no state came back with the code, the CSRF check is skipped");
+        client.csrf().to_string()
+    })
+}
+
+
+// LinkedIn answers invalid_grant when the exchange names another redirect
+// URL than the authorization did, e.g. when the URL was generated by a run
+// with different settings.
+fn warn_redirect_mismatch(reporter: &Reporter, redirect_url: &str, pasted: &RedirectParams) {
+    if let (false, Some(pasted_url)) = (pasted.redirect_matches(redirect_url), &pasted.redirect_url) {
+        reporter.warn(&format!("the code was issued for the redirect url {} but --redirect-url is {}, \
+                                LinkedIn rejects the exchange unless they match", pasted_url, redirect_url));
+    }
+}
+
+
+// On stderr, so the command can be copied while stdout still carries the
+// URL or the token.
+fn print_curl(args: &ArgMatches, reporter: &Reporter, client: &LinkedInAuthClient, code: &str) {
+    if flag(args, "print-curl") {
+        reporter.note(&format!("{}\n", client.exchange_code_curl(code, reporter.show_token)));
+    }
+}
+
+
+// Ends the run with a message on Ctrl-C while waiting for the code instead of
+// dying mid-line. Exiting closes the local server's listener, which is bound
+// with SO_REUSEADDR so the port is free for the next run right away.
+fn exit_on_interrupt(reporter: &Reporter) {
+    let interrupted = reporter.clone();
+    let result = ctrlc::set_handler(move || {
+        interrupted.note("\nCancelled.");
+        process::exit(EXIT_INTERRUPTED);
+    });
+    if let Err(err) = result {
+        reporter.warn(&format!("cannot handle Ctrl-C: {}", err));
+    }
+}
+
+
+// Exchanges the code and caches, reports and optionally verifies the token.
+async fn finish_authorization(args: &ArgMatches, reporter: &Reporter, client: &LinkedInAuthClient,
+                              cache: Option<&mut TokenCache>, key: &str, code: &str,
+                              state: &str) -> Result<(), Box<dyn Error>> {
+    print_curl(args, reporter, client, code);
+    time_connect(args);
+    let request = client.exchange_code(code, state);
+    let token = with_spinner(args, "token", "Exchanging the code...", request).await?;
+    warn_token_type(reporter, &token);
+    warn_missing_scopes(reporter, &token, client.scopes());
+    if flag(args, "offline") {
+        warn_missing_refresh_token(reporter, &token);
+    }
+
+    if let Some(cache) = cache {
+        cache.insert(key, &token);
+        if let Err(err) = cache.save() {
+            reporter.warn(&format!("cannot save the token cache: {}", err));
+        }
+    }
+
+    // The code is used up, so is the session that led to it.
+    if let Some(path) = session_path(args) {
+        if let Err(err) = Session::remove(&path) {
+            reporter.warn(&format!("cannot remove the session file: {}", err));
+        }
+    }
+
+    report_token(args, reporter, &token)?;
+    if flag(args, "verify") {
+        verify_token(args, reporter, client, &token).await?;
+    }
+    Ok(())
+}
+
+
+async fn client_credentials_controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let config = load_config(args)?;
+    let client_id = client_id(args, &config)?;
+    let client_secret = client_secret(args, &config)?;
+
+    let client = auth_client(args, reporter, client_id, &client_secret, "", &[])?;
+    time_connect(args);
+    let token = with_spinner(args, "token", "Requesting the token...", client.client_credentials()).await?;
+    warn_token_type(reporter, &token);
+
+    report_token(args, reporter, &token)
+}
+
+
+async fn refresh_controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    warn_ignored_pretty(args, reporter);
+    let config = load_config(args)?;
+    let client_id = client_id(args, &config)?;
+    let client_secret = client_secret(args, &config)?;
+    let refresh_token = value(args, "refresh-token").unwrap();
+
+    let client = auth_client(args, reporter, client_id, &client_secret, "", &[])?;
+    time_connect(args);
+    let token = with_spinner(args, "token", "Refreshing the token...", client.refresh(refresh_token)).await?;
+    warn_token_type(reporter, &token);
+
+    report_token(args, reporter, &token)
+}
+
+
+// Runs the authorization once per [[batch]] entry. The member stays signed
+// in to LinkedIn in the browser, so after the first entry only the consent
+// screen is shown, and a single local server captures every redirect.
+async fn batch_controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let config = load_config(args)?;
+    if config.batch.is_empty() {
+        return Err(EmptyBatch.into());
+    }
+    let client_id = client_id(args, &config)?;
+    let client_secret = client_secret(args, &config)?;
+    let redirect_url = setting(args, "redirect-url", &config.redirect_url).unwrap();
+    validate_redirect_url(redirect_url)?;
+    // Every entry is checked up front, so a typo in the last one does not
+    // cost the authorizations before it.
+    let mut entry_scopes = Vec::new();
+    for entry in &config.batch {
+        let mut scopes = entry.scopes.clone();
+        normalize_scopes(&mut scopes);
+        validate_scopes(&scopes.iter().map(String::as_str).collect::<Vec<_>>())?;
+        entry_scopes.push(scopes);
+    }
+
+    let max_requests = value(args, "max-callback-requests").unwrap().parse()?;
+    let server = match flag(args, "no-server") {
+        true => None,
+        false => callback_address(redirect_url)
+            .map(|address| CallbackServer::bind(address).map(|server| server.with_max_requests(max_requests)))
+            .transpose()?,
+    };
+    let output_dir = Path::new(value(args, "output-dir").unwrap());
+    exit_on_interrupt(reporter);
+
+    let mut tokens = Vec::new();
+    for (entry, scopes) in config.batch.iter().zip(&entry_scopes) {
+        let scopes: Vec<&str> = scopes.iter().map(String::as_str).collect();
+        let client = auth_client(args, reporter, client_id, &client_secret, redirect_url, &scopes)?;
+        let url = client.authorization_url();
+
+        reporter.say(&format!("\n[{}]", entry.name));
+        reporter.report_auth_url(&url, false)?;
+        if flag(args, "open") {
+            if let Err(err) = webbrowser::open(&url) {
+                reporter.warn(&format!("cannot open the browser, please open the URL manually: {}", err));
+            }
+        }
+
+        let (code, state) = match &server {
+            Some(server) => (server.wait(client.csrf(), None)?, client.csrf().to_string()),
+            None => {
+                reporter.say("Please, copy the 'code' value (or the whole redirect URL) and pass it here:\n");
+                let reprompt = || reporter.say("The code is empty, please paste it again:\n");
+                let pasted = read_code(&mut io::stdin().lock(), reprompt)?;
+                warn_redirect_mismatch(reporter, redirect_url, &pasted);
+                (pasted.code, returned_state(reporter, &client, pasted.state))
+            },
+        };
+
+        let request = client.exchange_code(&code, &state);
+        let token = with_spinner(args, "token", "Exchanging the code...", request).await?;
+        warn_token_type(reporter, &token);
+        warn_missing_scopes(reporter, &token, client.scopes());
+
+        let path = output_dir.join(entry.output.clone().unwrap_or_else(|| format!("{}.json", entry.name)));
+        write_private_file(&path, &serde_json::to_vec(&token)?)?;
+        tokens.push((entry.name.as_str(), token, path));
+    }
+
+    print_batch_summary(reporter, &tokens);
+    Ok(())
+}
+
+
+fn print_batch_summary(reporter: &Reporter, tokens: &[(&str, TokenResponse, PathBuf)]) {
+    let mut rows = vec![["NAME", "ACCESS KEY", "EXPIRES", "FILE"].map(str::to_string)];
+    for (name, token, path) in tokens {
+        let access_key = match reporter.show_token {
+            true => token.access_token.clone(),
+            false => mask_token(&token.access_token),
+        };
+        let expires = token.expires_at
+            .and_then(|expires_at| Local.timestamp_opt(expires_at as i64, 0).single())
+            .map_or_else(|| "unknown".to_string(), |expires_at| expires_at.format("%Y-%m-%d %H:%M").to_string());
+        rows.push([name.to_string(), access_key, expires, path.display().to_string()]);
+    }
+
+    let width = |column: usize| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or_default();
+    let (name_width, key_width, expires_width) = (width(0), width(1), width(2));
+    for [name, access_key, expires, path] in &rows {
+        reporter.say(&format!("{:<name_width$}  {:<key_width$}  {:<expires_width$}  {}",
+                              name, access_key, expires, path,
+                              name_width = name_width, key_width = key_width, expires_width = expires_width));
+    }
+}
+
+
+async fn revoke_controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let config = load_config(args)?;
+    let client_id = client_id(args, &config)?;
+    let client_secret = client_secret(args, &config)?;
+    let token = value(args, "token").unwrap();
+
+    let client = auth_client(args, reporter, client_id, &client_secret, "", &[])?;
+    client.revoke(token).await?;
+    reporter.say("\nToken revoked.");
+
+    if let Some(mut cache) = token_cache(args, reporter, client_id) {
+        if cache.remove_token(token) {
+            match cache.save() {
+                Ok(()) => reporter.say("Removed it from the token cache."),
+                Err(err) => reporter.warn(&format!("cannot save the token cache: {}", err)),
+            }
+        }
+    }
+    Ok(())
+}
+
+
+async fn whoami_controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let config = load_config(args)?;
+    let client_id = client_id(args, &config)?;
+    let permissions = permissions(args, &config)?;
+    let permissions: Vec<&str> = permissions.iter().map(String::as_str).collect();
+
+    let token = match value(args, "token-file") {
+        Some(path) => {
+            let mut token: TokenResponse = serde_json::from_slice(&fs::read(path)?)?;
+            if token.is_expired() {
+                return Err(StoredTokenExpired { refreshable: token.refresh_token.is_some() }.into());
+            }
+            // `expires_in` counts from when the file was written.
+            token.expires_in = token.seconds_left();
+            token
+        },
+        None => {
+            let cache = token_cache(args, reporter, client_id).ok_or(NoStoredToken)?;
+            let key = cache_key(config.profile.as_deref(), client_id, &permissions);
+            let entry = cache.entry(&key).ok_or(NoStoredToken)?;
+            if entry.is_expired() {
+                return Err(StoredTokenExpired { refreshable: entry.token.refresh_token.is_some() }.into());
+            }
+            cache.get(&key).ok_or(NoStoredToken)?
+        },
+    };
+
+    let client = auth_client(args, reporter, client_id, "", "", &permissions)?;
+    let request = client.fetch_member(&token.access_token);
+    let member = with_spinner(args, "member", "Reading the member...", request).await?;
+    reporter.say(&format!("Signed in as {} ({}).", member.name, member.id));
+    if token.expires_in.is_some() {
+        reporter.say(&format!("Access key {}.", describe_expiry(token.expires_in)));
+    }
+    Ok(())
+}
+
+
+// id_tokens are JWTs and can be read without LinkedIn, access keys are
+// opaque: only a call tells whether they work, and which profile endpoint
+// answers tells their scopes apart.
+async fn inspect_controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let token = value(args, "token").unwrap().trim();
+    if let Ok(claims) = decode_claims(token) {
+        print_claims(reporter, &claims);
+        return Ok(());
+    }
+
+    reporter.say("The token is not a JWT, only LinkedIn can tell whether it works.");
+    if flag(args, "offline") {
+        return Ok(());
+    }
+
+    let client = auth_client(args, reporter, "", "", "", &["openid"])?;
+    let request = client.fetch_member(token);
+    let member = match with_spinner(args, "member", "Asking LinkedIn...", request).await {
+        Ok(member) => Ok((member, "openid and profile, read through the userinfo endpoint")),
+        Err(AuthError::Profile { status: StatusCode::FORBIDDEN, .. }) => {
+            let client = auth_client(args, reporter, "", "", "", &[])?;
+            let request = client.fetch_member(token);
+            with_spinner(args, "member", "Asking LinkedIn...", request).await
+                .map(|member| (member, "r_liteprofile, read through the me endpoint"))
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    match member {
+        Ok((member, scopes)) => {
+            reporter.say(&format!("The access key works for {} ({}).", member.name, member.id));
+            reporter.say(&format!("It grants {}, LinkedIn does not list its other scopes.", scopes));
+        },
+        Err(AuthError::Profile { status: StatusCode::FORBIDDEN, .. }) => {
+            reporter.say("The access key works, but grants neither openid and profile nor r_liteprofile, \
+                          so LinkedIn does not tell its scopes.");
+        },
+        Err(err) => return Err(err.into()),
+    }
+    Ok(())
+}
+
+
+// `exp`, `iat` and `nbf` are Unix timestamps, shown as local time.
+fn print_claims(reporter: &Reporter, claims: &Value) {
+    reporter.say("JWT claims (decoded only, the signature is not verified):");
+    let now = Utc::now().timestamp();
+    for (claim, value) in claims.as_object().into_iter().flatten() {
+        let time = value.as_i64().filter(|_| ["exp", "iat", "nbf"].contains(&claim.as_str()));
+        let shown = match (claim.as_str(), time) {
+            ("exp", Some(exp)) if exp > now => describe_expiry(Some((exp - now) as u64)),
+            ("exp", Some(exp)) => format!("expired {} ago", describe_duration((now - exp) as u64)),
+            (_, Some(time)) => local_time(time),
+            (_, None) => match value.as_str() {
+                Some(text) => text.to_string(),
+                None => value.to_string(),
+            },
+        };
+        reporter.say(&format!("    {}: {}", claim, shown));
+    }
+}
+
+
+fn local_time(timestamp: i64) -> String {
+    match Local.timestamp_opt(timestamp, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M local").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+
+// Prints one line of the `doctor` checklist and tells whether it passed.
+fn report_check(reporter: &Reporter, name: &str, result: Result<String, Box<dyn Error>>) -> bool {
+    match &result {
+        Ok(detail) => reporter.say(&format!("\u{2705} {}: {}.", name, detail)),
+        Err(err) => reporter.say(&format!("\u{274c} {}: {}.", name, redact(&err.to_string()))),
+    }
+    result.is_ok()
+}
+
+
+fn check_connection(auth_url: &str, timeout: Duration) -> Result<String, Box<dyn Error>> {
+    let url = Url::parse(auth_url)?;
+    let host = url.host_str().unwrap_or_default();
+    let address = (host, url.port_or_known_default().unwrap_or(443)).to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} has no address", host)))?;
+    TcpStream::connect_timeout(&address, timeout)?;
+    Ok(format!("{} resolves to {} and accepts connections", host, address.ip()))
+}
+
+
+fn check_client_id(client_id: &str) -> Result<String, Box<dyn Error>> {
+    validate_client_id(client_id)?;
+    Ok(format!("'{}' is well-formed", client_id))
+}
+
+
+fn check_redirect_url(redirect_url: &str) -> Result<String, Box<dyn Error>> {
+    validate_redirect_url(redirect_url)?;
+    Ok(match callback_address(redirect_url) {
+        Some(address) => format!("{}, the code is captured on port {}", redirect_url, address.port),
+        None => format!("{}, the code has to be pasted", redirect_url),
+    })
+}
+
+
+// Exchanging a made up code is harmless: LinkedIn rejects the code itself
+// with `invalid_request` or `invalid_grant` once it has accepted the
+// credentials, and answers `invalid_client` otherwise.
+async fn check_credentials(client: &LinkedInAuthClient) -> Result<String, Box<dyn Error>> {
+    match client.exchange_code("lin-auth-doctor", client.csrf()).await {
+        Ok(_) => Ok("LinkedIn accepted the client id and secret".to_string()),
+        Err(AuthError::OAuth { error, .. }) if error != "invalid_client" => {
+            Ok("LinkedIn accepted the client id and secret".to_string())
+        },
+        Err(err) => Err(err.into()),
+    }
+}
+
+
+async fn doctor_controller(args: &ArgMatches, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let config = load_config(args)?;
+    let redirect_url = setting(args, "redirect-url", &config.redirect_url).unwrap();
+    let timeout = Duration::from_secs(value(args, "timeout").unwrap().parse()?);
+
+    let mut failed = 0;
+    let mut check = |name: &str, result| failed += usize::from(!report_check(reporter, name, result));
+
+    check("Connection", check_connection(value(args, "auth-url").unwrap(), timeout));
+    let client_id = client_id(args, &config).ok();
+    check("Client id", match client_id {
+        Some(client_id) => check_client_id(client_id),
+        None => Err(MissingClientId.into()),
+    });
+    check("Redirect URL", check_redirect_url(redirect_url));
+
+    // The secret is never prompted for, the check only runs when it is given.
+    match (client_id, setting(args, "client-secret", &config.client_secret)) {
+        (Some(client_id), Some(client_secret)) => {
+            let client = auth_client(args, reporter, client_id, client_secret, redirect_url, &[])?;
+            check("Credentials", check_credentials(&client).await);
+        },
+        _ => reporter.say("\u{2796} Credentials: not checked, pass --client-secret to check them."),
+    }
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(ChecksFailed(failed).into()),
+    }
+}
+
+
+fn auth_exit_code(err: &AuthError) -> u8 {
+    match err {
+        AuthError::Http(_) => EXIT_NETWORK,
+        AuthError::OAuth { .. } | AuthError::Status(_) | AuthError::Profile { .. }
+        | AuthError::Organization { .. } => EXIT_LINKEDIN,
+        AuthError::CsrfMismatch | AuthError::MissingCode | AuthError::InvalidRedirectUrl(_)
+        | AuthError::InvalidUrl(_) | AuthError::InvalidAuthParam(_) | AuthError::ReservedAuthParam(_)
+        | AuthError::InvalidVerifierLength(_) => EXIT_INPUT,
+        _ => EXIT_FAILURE,
+    }
+}
+
+
+fn exit_code(err: &(dyn Error + 'static)) -> u8 {
+    if let Some(err) = err.downcast_ref::<AuthError>() {
+        auth_exit_code(err)
+    } else if err.is::<ReqError>() {
+        EXIT_NETWORK
+    } else if err.is::<MissingTokenField>() {
+        EXIT_LINKEDIN
+    } else if err.is::<Timeout>() || err.is::<CallbackTimeout>() || err.is::<TooManyCallbackRequests>() {
+        EXIT_TIMEOUT
+    } else if err.is::<MissingClientId>() || err.is::<InvalidClientId>() || err.is::<MissingSecret>()
+        || err.is::<InvalidProxy>() || err.is::<InsecureLinkedIn>() || err.is::<EmptyBatch>()
+        || err.is::<CredentialsFileError>() || err.is::<InsecurePermissions>()
+        || err.is::<InvalidRedirectUrl>() || err.is::<UnknownScopes>()
+        || err.is::<InvalidVerifierLength>() || err.is::<InvalidAuthParam>()
+        || err.is::<ReservedAuthParam>() || err.is::<MalformedAuthParam>() || err.is::<ConfigError>()
+        || err.is::<ScopesFileError>() || err.is::<MissingCode>() || err.is::<EmptyCode>()
+        || err.is::<EmptyCodeFile>() || err.is::<PortInUse>() || err.is::<UnusablePort>()
+        || err.is::<InvalidListenAddr>() || err.is::<MalformedHeader>() || err.is::<ReservedHeader>()
+        || err.is::<ParseIntError>() || err.is::<url::ParseError>() {
+        EXIT_INPUT
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Parse errors are printed before the flag is read, so it is looked for
+    // by hand. clap honors NO_COLOR and a non-terminal stderr itself.
+    let color = match env::args_os().any(|arg| arg == "--no-color") {
+        true => ColorChoice::Never,
+        false => ColorChoice::Auto,
+    };
+    let jira_auth = match cli().color(color).try_get_matches() {
+        Ok(matches) => matches,
+        Err(err) if err.use_stderr() => {
+            eprint!("{}", err.render());
+            return ExitCode::from(EXIT_INPUT);
+        },
+        Err(err) => err.exit(),
+    };
+
+    // Built once from the flags of the command that runs, every controller
+    // reports through it.
+    let reporter = &reporter_for(jira_auth.subcommand().map_or(&jira_auth, |(_, args)| args));
+    let result = match jira_auth.subcommand() {
+        Some(("completions", args)) => {
+            let shell: Shell = value(args, "shell").unwrap().parse().unwrap();
+            clap_complete::generate(shell, &mut cli(), env!("CARGO_BIN_NAME"), &mut io::stdout());
+            Ok(())
+        },
+        Some(("refresh", args)) => {
+            init_logging(args);
+            let result = refresh_controller(args, reporter).await;
+            print_timings(args, reporter);
+            result
+        },
+        Some(("revoke", args)) => {
+            init_logging(args);
+            revoke_controller(args, reporter).await
+        },
+        Some(("whoami", args)) => {
+            init_logging(args);
+            whoami_controller(args, reporter).await
+        },
+        Some(("inspect", args)) => {
+            init_logging(args);
+            inspect_controller(args, reporter).await
+        },
+        Some(("doctor", args)) => {
+            init_logging(args);
+            doctor_controller(args, reporter).await
+        },
+        Some(("batch", args)) => {
+            init_logging(args);
+            batch_controller(args, reporter).await
+        },
+        _ => {
+            init_logging(&jira_auth);
+            let result = controller(&jira_auth, reporter).await;
+            print_timings(&jira_auth, reporter);
+            result
+        },
+    };
 
-    match controller(&jira_auth) {
-        Ok(()) => {},
-        Err(err) => eprintln!("\nApplication error: {}.", err)
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            reporter.error(err.as_ref());
+            ExitCode::from(exit_code(err.as_ref()))
+        },
     }
 }
@@ -0,0 +1,479 @@
+use std::time::Duration;
+
+use reqwest::{Client, Proxy, StatusCode};
+use reqwest::header::AUTHORIZATION;
+use tracing::{debug, trace};
+use url::Url;
+use url::form_urlencoded;
+
+use crate::{ACCESS_TOKEN_URL, API_URL, AUTH_URL, AuthError, AuthMethod, DEFAULT_REFRESH_BUFFER,
+            DISPLAY_VALUES, InvalidAuthParam, MANAGED_AUTH_PARAMS, Member, PROMPT_VALUES, REVOKE_URL,
+            ReservedAuthParam, TokenResponse};
+use crate::{curl_command, generate_auth_code_url, generate_csrf, generate_nonce, organization_acls_endpoint,
+            parse_member_body, parse_organization_acls, parse_revoke_body, parse_token_body,
+            place_credentials, profile_endpoint, set_auth_param, verify_nonce, verify_state};
+use crate::pkce::{InvalidVerifierLength, Pkce};
+use crate::redact::redact;
+use crate::retry::{DEFAULT_MAX_RETRIES, RetryOn, send_with_retries};
+use crate::session::Session;
+
+
+/// Holds the application credentials and the CSRF token of a single
+/// authorization flow. Requests are sent with the non-blocking
+/// `reqwest::Client` and need a tokio runtime, the blocking
+/// `LinkedInAuthClient` drives this client on one of its own.
+#[derive(Debug, Clone)]
+pub struct LinkedInAuthClient {
+    http: Client,
+    auth_url: Url,
+    token_url: Url,
+    revoke_url: Url,
+    api_url: Url,
+    max_retries: u32,
+    retry_jitter: bool,
+    refresh_buffer: Duration,
+    auth_method: AuthMethod,
+    client_id: String,
+    client_secret: String,
+    redirect_url: String,
+    scopes: Vec<String>,
+    csrf: String,
+    nonce: Option<String>,
+    pkce: Option<Pkce>,
+    auth_params: Vec<(String, String)>,
+}
+
+impl LinkedInAuthClient {
+    /// Starts a `LinkedInAuthClientBuilder`, an alternative to `new` and the
+    /// `with_*` methods.
+    pub fn builder() -> LinkedInAuthClientBuilder {
+        LinkedInAuthClientBuilder::default()
+    }
+
+    pub fn new(client_id: &str, client_secret: &str,
+               redirect_url: &str, scopes: &[&str]) -> LinkedInAuthClient {
+        let mut client = LinkedInAuthClient {
+            http: Client::new(),
+            auth_url: Url::parse(AUTH_URL).expect("AUTH_URL is a valid URL"),
+            token_url: Url::parse(ACCESS_TOKEN_URL).expect("ACCESS_TOKEN_URL is a valid URL"),
+            revoke_url: Url::parse(REVOKE_URL).expect("REVOKE_URL is a valid URL"),
+            api_url: Url::parse(API_URL).expect("API_URL is a valid URL"),
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_jitter: false,
+            refresh_buffer: DEFAULT_REFRESH_BUFFER,
+            auth_method: AuthMethod::Body,
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            redirect_url: redirect_url.to_string(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            csrf: generate_csrf(),
+            nonce: None,
+            pkce: None,
+            auth_params: Vec::new(),
+        };
+        client.nonce = generate_nonce(&client.scopes);
+        client
+    }
+
+    /// Makes every request to LinkedIn go through `http`, so timeouts and
+    /// other settings configured on it apply to the whole flow.
+    pub fn with_http_client(mut self, http: Client) -> LinkedInAuthClient {
+        self.http = http;
+        self
+    }
+
+    /// Points the client at other authorization and token endpoints, e.g.
+    /// a mock server.
+    pub fn with_endpoints(mut self, auth_url: &str, token_url: &str)
+                              -> Result<LinkedInAuthClient, AuthError> {
+        self.auth_url = Url::parse(auth_url)?;
+        self.token_url = Url::parse(token_url)?;
+        Ok(self)
+    }
+
+    /// Points `revoke` at another revocation endpoint.
+    pub fn with_revoke_url(mut self, revoke_url: &str) -> Result<LinkedInAuthClient, AuthError> {
+        self.revoke_url = Url::parse(revoke_url)?;
+        Ok(self)
+    }
+
+    /// Points the profile requests made by `fetch_member` at another API
+    /// base URL.
+    pub fn with_api_url(mut self, api_url: &str) -> Result<LinkedInAuthClient, AuthError> {
+        self.api_url = Url::parse(api_url)?;
+        Ok(self)
+    }
+
+    /// Sets how many times a request is repeated after a connection failure,
+    /// a timeout or a 429/5xx response, 3 by default. The code exchange is
+    /// only repeated when it could not connect.
+    pub fn with_max_retries(mut self, max_retries: u32) -> LinkedInAuthClient {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Waits a random time between zero and the exponential backoff before
+    /// each retry, so clients failing together do not retry together.
+    /// `Retry-After` is still honored as sent.
+    pub fn with_retry_jitter(mut self, retry_jitter: bool) -> LinkedInAuthClient {
+        self.retry_jitter = retry_jitter;
+        self
+    }
+
+    /// Sends the client credentials in a Basic `Authorization` header instead
+    /// of the form body with `AuthMethod::Basic`.
+    pub fn with_auth_method(mut self, auth_method: AuthMethod) -> LinkedInAuthClient {
+        self.auth_method = auth_method;
+        self
+    }
+
+    /// Sets how much life a token must have left for `ensure_fresh` to
+    /// return it unchanged, 5 minutes by default.
+    pub fn with_refresh_buffer(mut self, buffer: Duration) -> LinkedInAuthClient {
+        self.refresh_buffer = buffer;
+        self
+    }
+
+    /// Enables PKCE: the authorization URL carries an S256 code challenge and
+    /// the code exchange sends the verifier instead of the client secret.
+    pub fn with_pkce(mut self, verifier_length: usize)
+                         -> Result<LinkedInAuthClient, InvalidVerifierLength> {
+        self.pkce = Some(Pkce::generate(verifier_length)?);
+        Ok(self)
+    }
+
+    /// Sends `prompt` with the authorization URL, e.g. `consent` to ask the
+    /// member to approve the scopes again.
+    pub fn with_prompt(mut self, prompt: &str) -> Result<LinkedInAuthClient, InvalidAuthParam> {
+        set_auth_param(&mut self.auth_params, "prompt", prompt, PROMPT_VALUES)?;
+        Ok(self)
+    }
+
+    /// Sends `display` with the authorization URL, e.g. `popup` for a
+    /// sign in page sized for a popup window.
+    pub fn with_display(mut self, display: &str) -> Result<LinkedInAuthClient, InvalidAuthParam> {
+        set_auth_param(&mut self.auth_params, "display", display, DISPLAY_VALUES)?;
+        Ok(self)
+    }
+
+    /// Appends a custom parameter to the authorization URL, after the ones
+    /// the client manages itself. Those in `MANAGED_AUTH_PARAMS` are rejected.
+    pub fn with_auth_param(mut self, name: &str, value: &str)
+                               -> Result<LinkedInAuthClient, ReservedAuthParam> {
+        if MANAGED_AUTH_PARAMS.contains(&name) {
+            return Err(ReservedAuthParam(name.to_string()));
+        }
+        self.auth_params.push((name.to_string(), value.to_string()));
+        Ok(self)
+    }
+
+    /// Replaces the generated `state` value, so a code obtained with the URL
+    /// of an earlier run can still be exchanged.
+    pub fn with_csrf(mut self, csrf: &str) -> LinkedInAuthClient {
+        self.csrf = csrf.to_string();
+        self
+    }
+
+    /// Restores the `state`, PKCE verifier and nonce of the run that generated
+    /// the authorization URL, so that run's code can be exchanged.
+    pub fn with_session(mut self, session: &Session) -> LinkedInAuthClient {
+        self.csrf = session.csrf.clone();
+        self.pkce = session.code_verifier.clone().map(Pkce::from_verifier);
+        self.nonce = session.nonce.clone();
+        self
+    }
+
+    /// The values `with_session` needs to exchange a code for the current
+    /// authorization URL in another process.
+    pub fn session(&self) -> Session {
+        Session {
+            client_id: self.client_id.clone(),
+            csrf: self.csrf.clone(),
+            code_verifier: self.pkce.as_ref().map(|pkce| pkce.verifier().to_string()),
+            nonce: self.nonce.clone(),
+        }
+    }
+
+    /// The `state` value sent with the authorization URL.
+    pub fn csrf(&self) -> &str {
+        &self.csrf
+    }
+
+    /// The scopes requested with the authorization URL.
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    /// The OpenID Connect `nonce` sent with the authorization URL, only set
+    /// when `openid` is among the scopes.
+    pub fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+
+    pub fn authorization_url(&self) -> String {
+        let mut params: Vec<(&str, &str)> = self.nonce().map(|nonce| ("nonce", nonce)).into_iter().collect();
+        params.extend(self.auth_params.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+        generate_auth_code_url(&self.auth_url, &self.client_id, &self.redirect_url, &self.scopes,
+                               &self.csrf, &params, self.pkce.as_ref())
+    }
+
+    /// Exchanges the authorization code for an access token, checking that
+    /// `state` is the one this client generated and, for OpenID logins, that
+    /// the `id_token` carries its nonce.
+    pub async fn exchange_code(&self, code: &str, state: &str) -> Result<TokenResponse, AuthError> {
+        verify_state(&self.csrf, Some(state))?;
+
+        // The code is single-use, a request LinkedIn may have processed is
+        // not sent again.
+        let token = self.post_token_form(&self.exchange_code_form(code), RetryOn::Connect).await?;
+
+        verify_nonce(self.nonce(), &token)?;
+        Ok(token)
+    }
+
+    /// The `curl` command for the request `exchange_code` sends, see
+    /// `curl_command`.
+    pub fn exchange_code_curl(&self, code: &str, show_secrets: bool) -> String {
+        curl_command(&self.token_url, &self.exchange_code_form(code), self.auth_method, show_secrets)
+    }
+
+    fn exchange_code_form<'a>(&'a self, code: &'a str) -> [(&'a str, &'a str); 6] {
+        // With PKCE the code verifier takes the place of the client secret.
+        let credential = match &self.pkce {
+            Some(pkce) => ("code_verifier", pkce.verifier()),
+            None => ("client_secret", self.client_secret.as_str()),
+        };
+        [("grant_type", "authorization_code"), ("code", code),
+         ("client_id", &self.client_id), credential,
+         ("redirect_uri", &self.redirect_url), ("state", &self.csrf)]
+    }
+
+    /// Obtains an application access token with the two-legged
+    /// client_credentials grant, no member sign in is involved.
+    pub async fn client_credentials(&self) -> Result<TokenResponse, AuthError> {
+        self.post_token_form(
+            &[("grant_type", "client_credentials"), ("client_id", &self.client_id),
+              ("client_secret", &self.client_secret)],
+            RetryOn::Transient
+        ).await
+    }
+
+    /// Obtains a new access token with a previously issued refresh token.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<TokenResponse, AuthError> {
+        self.post_token_form(
+            &[("grant_type", "refresh_token"), ("refresh_token", refresh_token),
+              ("client_id", &self.client_id), ("client_secret", &self.client_secret)],
+            RetryOn::Transient
+        ).await
+    }
+
+    /// Returns `token` while it has more than the refresh buffer of life
+    /// left, otherwise a new token obtained with its refresh token.
+    pub async fn ensure_fresh(&self, token: &TokenResponse) -> Result<TokenResponse, AuthError> {
+        if !token.expires_within(self.refresh_buffer) {
+            return Ok(token.clone());
+        }
+        match &token.refresh_token {
+            Some(refresh_token) => self.refresh(refresh_token).await,
+            None => Err(AuthError::MissingRefreshToken),
+        }
+    }
+
+    /// Reads the member behind `access_token`, from `userinfo` when the
+    /// client asks for `openid` and from `me` otherwise.
+    pub async fn fetch_member(&self, access_token: &str) -> Result<Member, AuthError> {
+        let url = profile_endpoint(&self.api_url, &self.scopes)?;
+        debug!("GET {}", url);
+
+        let response = send_with_retries(
+            || self.http.get(url.clone()).bearer_auth(access_token).send(),
+            RetryOn::Transient,
+            self.max_retries,
+            self.retry_jitter
+        ).await?;
+
+        let status = response.status();
+        debug!("HTTP status {}", status);
+        parse_member_body(status, &response.text().await?)
+    }
+
+    /// Checks that the member behind `access_token` holds an approved role in
+    /// `organization`, an `urn:li:organization:<id>` URN.
+    pub async fn check_organization(&self, access_token: &str, organization: &str) -> Result<(), AuthError> {
+        let url = organization_acls_endpoint(&self.api_url)?;
+        debug!("GET {}", url);
+
+        let response = send_with_retries(
+            || self.http.get(url.clone()).bearer_auth(access_token).send(),
+            RetryOn::Transient,
+            self.max_retries,
+            self.retry_jitter
+        ).await?;
+
+        let status = response.status();
+        debug!("HTTP status {}", status);
+        parse_organization_acls(status, &response.text().await?, organization)
+    }
+
+    /// Invalidates an access or refresh token issued to this application.
+    pub async fn revoke(&self, token: &str) -> Result<(), AuthError> {
+        let (status, body) = self.post_form(
+            &self.revoke_url,
+            &[("token", token), ("client_id", &self.client_id),
+              ("client_secret", &self.client_secret)],
+            RetryOn::Transient
+        ).await?;
+        parse_revoke_body(status, &body)
+    }
+
+    async fn post_token_form(&self, params: &[(&str, &str)], retry_on: RetryOn)
+                                 -> Result<TokenResponse, AuthError> {
+        let (status, body) = self.post_form(&self.token_url, params, retry_on).await?;
+        parse_token_body(status, &body)
+    }
+
+    async fn post_form(&self, url: &Url, params: &[(&str, &str)], retry_on: RetryOn)
+                           -> Result<(StatusCode, String), AuthError> {
+        let (params, authorization) = place_credentials(params, self.auth_method);
+        debug!("POST {}", redact(url.as_str()));
+        trace!(
+            "request body: {}",
+            redact(&form_urlencoded::Serializer::new(String::new()).extend_pairs(&params).finish())
+        );
+
+        let response = send_with_retries(
+            || {
+                let request = self.http.post(url.clone()).form(&params);
+                match &authorization {
+                    Some(authorization) => request.header(AUTHORIZATION, authorization),
+                    None => request,
+                }.send()
+            },
+            retry_on,
+            self.max_retries,
+            self.retry_jitter
+        ).await?;
+
+        let status = response.status();
+        debug!("HTTP status {}", status);
+        Ok((status, response.text().await?))
+    }
+}
+
+
+/// Builds a `LinkedInAuthClient` field by field. `client_id` and
+/// `client_secret` are required, `redirect_url` and the scopes only matter
+/// for the authorization code flow.
+///
+/// ```
+/// use lin_auth::nonblocking::LinkedInAuthClient;
+///
+/// let client = LinkedInAuthClient::builder()
+///     .client_id("client-id")
+///     .client_secret("client-secret")
+///     .redirect_url("https://localhost:8000")
+///     .scope("r_ads")
+///     .scope("r_ads_reporting")
+///     .timeout(std::time::Duration::from_secs(10))
+///     .build()?;
+///
+/// assert!(client.authorization_url().contains("scope=r_ads+r_ads_reporting"));
+/// # Ok::<(), lin_auth::AuthError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LinkedInAuthClientBuilder {
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    redirect_url: Option<String>,
+    scopes: Vec<String>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    auth_url: Option<String>,
+    token_url: Option<String>,
+    prompt: Option<String>,
+    display: Option<String>,
+}
+
+impl LinkedInAuthClientBuilder {
+    pub fn client_id(mut self, client_id: &str) -> LinkedInAuthClientBuilder {
+        self.client_id = Some(client_id.to_string());
+        self
+    }
+
+    pub fn client_secret(mut self, client_secret: &str) -> LinkedInAuthClientBuilder {
+        self.client_secret = Some(client_secret.to_string());
+        self
+    }
+
+    pub fn redirect_url(mut self, redirect_url: &str) -> LinkedInAuthClientBuilder {
+        self.redirect_url = Some(redirect_url.to_string());
+        self
+    }
+
+    /// Adds a scope, call it once per scope.
+    pub fn scope(mut self, scope: &str) -> LinkedInAuthClientBuilder {
+        self.scopes.push(scope.to_string());
+        self
+    }
+
+    /// Timeout of every request made to LinkedIn.
+    pub fn timeout(mut self, timeout: Duration) -> LinkedInAuthClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// An `http://` or `socks5://` proxy for every request.
+    pub fn proxy(mut self, proxy: &str) -> LinkedInAuthClientBuilder {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    pub fn auth_url(mut self, auth_url: &str) -> LinkedInAuthClientBuilder {
+        self.auth_url = Some(auth_url.to_string());
+        self
+    }
+
+    pub fn token_url(mut self, token_url: &str) -> LinkedInAuthClientBuilder {
+        self.token_url = Some(token_url.to_string());
+        self
+    }
+
+    /// One of `PROMPT_VALUES`, checked by `build`.
+    pub fn prompt(mut self, prompt: &str) -> LinkedInAuthClientBuilder {
+        self.prompt = Some(prompt.to_string());
+        self
+    }
+
+    /// One of `DISPLAY_VALUES`, checked by `build`.
+    pub fn display(mut self, display: &str) -> LinkedInAuthClientBuilder {
+        self.display = Some(display.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<LinkedInAuthClient, AuthError> {
+        let client_id = self.client_id.ok_or(AuthError::MissingField("client_id"))?;
+        let client_secret = self.client_secret.ok_or(AuthError::MissingField("client_secret"))?;
+
+        let mut http = Client::builder();
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            http = http.proxy(Proxy::all(proxy)?);
+        }
+
+        let scopes: Vec<&str> = self.scopes.iter().map(String::as_str).collect();
+        let mut client = LinkedInAuthClient::new(&client_id, &client_secret,
+                                                 self.redirect_url.as_deref().unwrap_or_default(), &scopes)
+            .with_http_client(http.build()?)
+            .with_endpoints(self.auth_url.as_deref().unwrap_or(AUTH_URL),
+                            self.token_url.as_deref().unwrap_or(ACCESS_TOKEN_URL))?;
+        if let Some(prompt) = &self.prompt {
+            client = client.with_prompt(prompt)?;
+        }
+        if let Some(display) = &self.display {
+            client = client.with_display(display)?;
+        }
+        Ok(client)
+    }
+}
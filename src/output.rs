@@ -0,0 +1,225 @@
+use std::error::Error;
+use std::fmt;
+
+use chrono::{Duration as ChronoDuration, Local, Utc};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::TokenResponse;
+use crate::id_token::decode_claims;
+use crate::redact::{mask_token, redact};
+use crate::shell_quote;
+use crate::template::render_token;
+
+
+#[derive(Debug)]
+pub struct MissingTokenField(pub String);
+
+impl fmt::Display for MissingTokenField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the token response has no {}", self.0)
+    }
+}
+
+impl Error for MissingTokenField {}
+
+
+/// What stdout carries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Mode {
+    /// The report meant for a person, instructions included.
+    #[default]
+    Human,
+    /// The token response as JSON.
+    Json,
+    /// `export` lines of the token variables.
+    Env,
+    /// The token rendered through a template, see `render_token`.
+    Template(String),
+    /// A single field of the token response.
+    Field(String),
+    /// Nothing, the token only goes to a file.
+    Silent,
+}
+
+
+/// Everything the command line tells the user goes through here, so `--quiet`,
+/// `--json` and the other output flags apply the same way everywhere. Data
+/// goes to stdout, instructions to stdout only in `Mode::Human`, warnings and
+/// errors always to stderr.
+#[derive(Debug, Clone, Default)]
+pub struct Reporter {
+    pub mode: Mode,
+    /// Drops the instructions and reduces the human report to the bare token.
+    pub quiet: bool,
+    /// Indents JSON output.
+    pub pretty: bool,
+    /// Prints the whole tokens in the human report instead of their ends.
+    pub show_token: bool,
+}
+
+impl Reporter {
+    /// Prints an instruction or progress message.
+    pub fn say(&self, message: &str) {
+        match (self.quiet, &self.mode) {
+            (true, _) => {},
+            (false, Mode::Human) => println!("{}", message),
+            (false, _) => eprintln!("{}", message),
+        }
+    }
+
+    /// Prints `message` as a warning, `--quiet` does not hide them.
+    pub fn warn(&self, message: &str) {
+        eprintln!("Warning: {}.", message);
+    }
+
+    /// Prints output a flag asked for on stderr, e.g. `--timings`, so it
+    /// neither mixes with stdout nor is hidden by `--quiet`.
+    pub fn note(&self, message: &str) {
+        eprintln!("{}", message);
+    }
+
+    /// Prints the error ending the run. reqwest errors quote the request URL,
+    /// which may carry secrets, so they are redacted.
+    pub fn error(&self, err: &dyn Error) {
+        eprintln!("\nApplication error: {}.", redact(&err.to_string()));
+    }
+
+    /// Compact JSON for piping, indented with `pretty`.
+    pub fn to_json<T: Serialize>(&self, value: &T) -> serde_json::Result<String> {
+        match self.pretty {
+            true => serde_json::to_string_pretty(value),
+            false => serde_json::to_string(value),
+        }
+    }
+
+    /// Prints the authorization URL: on its own with `dry_run`, otherwise
+    /// with the instructions. `--quiet` still prints it to stderr since it is
+    /// needed to sign in.
+    pub fn report_auth_url(&self, url: &str, dry_run: bool) -> serde_json::Result<()> {
+        match (dry_run, &self.mode) {
+            (true, Mode::Json) => println!("{}", self.to_json(&json!({ "authorization_url": url }))?),
+            (true, _) => println!("{}", url),
+            (false, _) if self.quiet => eprintln!("{}", url),
+            (false, _) => self.say(&format!(
+                "\nGenerated URL to request the LIN authorization code for your application:\n\n\
+                {}\n\n\
+                Please, proceed with it and sign in with your account. \
+                After authorization, you'll be redirected to the page requested in CLI. \n",
+                url
+            )),
+        }
+        Ok(())
+    }
+
+    /// Prints the token in the current mode.
+    pub fn report_token(&self, token: &TokenResponse) -> Result<(), Box<dyn Error>> {
+        match &self.mode {
+            Mode::Json => println!("{}", self.to_json(token)?),
+            Mode::Env => {
+                for (name, value) in token_variables(token) {
+                    println!("export {}={}", name, shell_quote(&value));
+                }
+            },
+            Mode::Template(template) => println!("{}", render_token(template, token)?),
+            Mode::Field(name) => println!("{}", token_field(token, name)?),
+            Mode::Silent => {},
+            Mode::Human if self.quiet => println!("{}", token.access_token),
+            Mode::Human => self.print_token(token),
+        }
+        Ok(())
+    }
+
+    // Tokens end up in scrollback and screen shares, so only their ends are
+    // shown unless --show-token asks for them.
+    fn print_token(&self, token: &TokenResponse) {
+        let shown = |token: &str| match self.show_token {
+            true => token.to_string(),
+            false => mask_token(token),
+        };
+        println!("\nAccess key retrieved successfuly:\n\n{}.\n", shown(&token.access_token));
+
+        println!("Access key {}.", describe_expiry(token.expires_in));
+        if let Some(scope) = &token.scope {
+            println!("Granted scopes: {}.", scope);
+        }
+        if let Some(refresh_token) = &token.refresh_token {
+            println!("Refresh token: {}.", shown(refresh_token));
+            println!("Refresh token {}.", describe_expiry(token.refresh_token_expires_in));
+        }
+        if let Some(id_token) = &token.id_token {
+            print_id_token(id_token);
+        }
+        if !self.show_token {
+            println!("\nPass --show-token, --json or --output to get the whole access key.");
+        }
+        println!("\nYou can now use it.");
+    }
+}
+
+
+fn print_id_token(id_token: &str) {
+    let claims = match decode_claims(id_token) {
+        Ok(claims) => claims,
+        Err(err) => return println!("ID token: {}.", err),
+    };
+
+    println!("ID token claims (decoded only, the signature is not verified):");
+    for claim in &["sub", "name", "email"] {
+        if let Some(value) = claims[claim].as_str() {
+            println!("    {}: {}", claim, value);
+        }
+    }
+}
+
+
+// Strings are printed without their JSON quotes.
+fn token_field(token: &TokenResponse, name: &str) -> Result<String, Box<dyn Error>> {
+    match serde_json::to_value(token)?.get(name) {
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(value) => Ok(value.to_string()),
+        None => Err(MissingTokenField(name.to_string()).into()),
+    }
+}
+
+
+/// The variables printed by `Mode::Env` and written by `--dotenv`.
+pub fn token_variables(token: &TokenResponse) -> Vec<(&'static str, String)> {
+    let mut variables = vec![("LINKEDIN_ACCESS_TOKEN", token.access_token.clone())];
+    if let Some(refresh_token) = &token.refresh_token {
+        variables.push(("LINKEDIN_REFRESH_TOKEN", refresh_token.clone()));
+    }
+    if let Some(expires_in) = token.expires_in {
+        let expires_at = Utc::now().timestamp() + expires_in as i64;
+        variables.push(("LINKEDIN_TOKEN_EXPIRES_AT", expires_at.to_string()));
+    }
+    variables
+}
+
+
+/// `seconds` in the largest whole unit, e.g. `2 hours`.
+pub fn describe_duration(seconds: u64) -> String {
+    let (amount, unit) = match seconds {
+        0..=59 => (seconds, "second"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    match amount {
+        1 => format!("1 {}", unit),
+        _ => format!("{} {}s", amount, unit),
+    }
+}
+
+
+/// When a token living `expires_in` more seconds expires, in local time.
+pub fn describe_expiry(expires_in: Option<u64>) -> String {
+    match expires_in {
+        Some(seconds) => {
+            let expires_at = Local::now() + ChronoDuration::seconds(seconds as i64);
+            format!("expires at {} local / in {}",
+                    expires_at.format("%Y-%m-%d %H:%M"), describe_duration(seconds))
+        },
+        None => "expiry unknown".to_string(),
+    }
+}
@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fmt;
+
+use base64::encode_config;
+use rand::{Rng, thread_rng};
+use sha2::{Digest, Sha256};
+
+pub const MIN_VERIFIER_LENGTH: usize = 43;
+pub const MAX_VERIFIER_LENGTH: usize = 128;
+
+const VERIFIER_CHARSET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+
+
+#[derive(Debug)]
+pub struct InvalidVerifierLength(pub usize);
+
+impl fmt::Display for InvalidVerifierLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PKCE code verifier must be between {} and {} characters long, got {}",
+               MIN_VERIFIER_LENGTH, MAX_VERIFIER_LENGTH, self.0)
+    }
+}
+
+impl Error for InvalidVerifierLength {}
+
+
+/// A PKCE code verifier together with its S256 code challenge.
+#[derive(Debug, Clone)]
+pub struct Pkce {
+    verifier: String,
+    challenge: String,
+}
+
+impl Pkce {
+    pub fn generate(length: usize) -> Result<Pkce, InvalidVerifierLength> {
+        if !(MIN_VERIFIER_LENGTH..=MAX_VERIFIER_LENGTH).contains(&length) {
+            return Err(InvalidVerifierLength(length));
+        }
+
+        let mut rng = thread_rng();
+        let verifier: String = (0..length)
+            .map(|_| VERIFIER_CHARSET[rng.gen_range(0..VERIFIER_CHARSET.len())] as char)
+            .collect();
+
+        Ok(Pkce::from_verifier(verifier))
+    }
+
+    pub fn from_verifier(verifier: String) -> Pkce {
+        let digest = Sha256::digest(verifier.as_bytes());
+        let challenge = encode_config(digest, base64::URL_SAFE_NO_PAD);
+        Pkce { verifier, challenge }
+    }
+
+    pub fn verifier(&self) -> &str {
+        &self.verifier
+    }
+
+    pub fn challenge(&self) -> &str {
+        &self.challenge
+    }
+}
@@ -0,0 +1,124 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::redirect::{RedirectParams, parse_redirect};
+
+/// How many empty lines are accepted before giving up on the code.
+pub const CODE_ATTEMPTS: u32 = 3;
+
+
+#[derive(Debug)]
+pub struct EmptyCode;
+
+impl fmt::Display for EmptyCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no authorization code was pasted after {} attempts", CODE_ATTEMPTS)
+    }
+}
+
+impl Error for EmptyCode {}
+
+
+#[derive(Debug)]
+pub struct Timeout;
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out waiting for the authorization code to be pasted")
+    }
+}
+
+impl Error for Timeout {}
+
+
+#[derive(Debug)]
+pub struct EmptyCodeFile(pub PathBuf);
+
+impl fmt::Display for EmptyCodeFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no authorization code was written to {}", self.0.display())
+    }
+}
+
+impl Error for EmptyCodeFile {}
+
+
+fn read_code_lines<L, F>(mut next_line: L, mut reprompt: F)
+                         -> Result<RedirectParams, Box<dyn Error>>
+    where L: FnMut() -> Result<Option<String>, Box<dyn Error>>, F: FnMut() {
+
+    for attempt in 1..=CODE_ATTEMPTS {
+        let line = match next_line()? {
+            Some(line) => line,
+            None => break,
+        };
+
+        match line.trim() {
+            "" if attempt < CODE_ATTEMPTS => reprompt(),
+            "" => {},
+            pasted => return Ok(parse_redirect(pasted)?),
+        }
+    }
+    Err(EmptyCode.into())
+}
+
+
+/// Reads the pasted code or redirect URL from `input`, calling `reprompt`
+/// after each empty line until `CODE_ATTEMPTS` lines were read.
+pub fn read_code<R, F>(input: &mut R, reprompt: F)
+                          -> Result<RedirectParams, Box<dyn Error>>
+    where R: BufRead, F: FnMut() {
+
+    read_code_lines(
+        || {
+            let mut line = String::new();
+            Ok((input.read_line(&mut line)? > 0).then_some(line))
+        },
+        reprompt
+    )
+}
+
+
+/// Same as `read_code` on stdin, failing with `Timeout` once `timeout` has
+/// passed. A blocked read cannot be interrupted, so stdin is read on a
+/// thread of its own.
+pub fn read_stdin_code<F>(timeout: Duration, reprompt: F)
+                             -> Result<RedirectParams, Box<dyn Error>>
+    where F: FnMut() {
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        let mut line = String::new();
+        let result = io::stdin().read_line(&mut line).map(|read| (read > 0).then_some(line));
+        let done = !matches!(result, Ok(Some(_)));
+        if sender.send(result).is_err() || done {
+            break;
+        }
+    });
+
+    let deadline = Instant::now() + timeout;
+    read_code_lines(
+        || match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => Ok(line?),
+            Err(RecvTimeoutError::Timeout) => Err(Timeout.into()),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+        },
+        reprompt
+    )
+}
+
+
+/// Reads the code or redirect URL another process wrote to `path`. For a
+/// named pipe this blocks until the writer closes it.
+pub fn read_code_file(path: &Path) -> Result<RedirectParams, Box<dyn Error>> {
+    match fs::read_to_string(path)?.trim() {
+        "" => Err(EmptyCodeFile(path.to_path_buf()).into()),
+        written => Ok(parse_redirect(written)?),
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+const MASK: &str = "***";
+
+// Query string and form parameters whose values must never be shown.
+const SECRET_PARAMS: &str =
+    "client_secret|code|code_verifier|access_token|refresh_token|id_token|token";
+// JSON fields of the token response whose values must never be shown.
+const SECRET_FIELDS: &str = "access_token|refresh_token|id_token";
+
+
+fn secret_params() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(&format!(r"(^|[?&\s])({})=[^&\s]*", SECRET_PARAMS)).unwrap()
+    })
+}
+
+
+fn secret_fields() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(&format!(r#""({})"\s*:\s*"[^"]*""#, SECRET_FIELDS)).unwrap()
+    })
+}
+
+
+/// Replaces secret values in URLs, form bodies and token JSON with `***`.
+pub fn redact(text: &str) -> String {
+    let text = secret_params().replace_all(text, format!("${{1}}${{2}}={}", MASK).as_str());
+    secret_fields().replace_all(&text, format!(r#""${{1}}":"{}""#, MASK).as_str()).into_owned()
+}
+
+
+/// Shortens a token to its first and last 4 characters, e.g. `AQVt...x9f0`,
+/// enough to tell tokens apart without revealing them. Short values are
+/// masked completely.
+pub fn mask_token(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    match chars.len() {
+        0..=11 => MASK.to_string(),
+        len => {
+            let start: String = chars[..4].iter().collect();
+            let end: String = chars[len - 4..].iter().collect();
+            format!("{}...{}", start, end)
+        },
+    }
+}
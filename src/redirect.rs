@@ -0,0 +1,54 @@
+use url::{Position, Url};
+
+use crate::AuthError;
+
+
+/// What the authorization redirect hands back: the `code`, the `state` and
+/// the redirect URL without its query, the latter two only when a whole URL
+/// rather than a bare code was given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedirectParams {
+    pub code: String,
+    pub state: Option<String>,
+    pub redirect_url: Option<String>,
+}
+
+impl RedirectParams {
+    /// Whether the code was issued for `redirect_url`, which LinkedIn insists
+    /// on at the token exchange. A bare code is assumed to match.
+    pub fn redirect_matches(&self, redirect_url: &str) -> bool {
+        match (&self.redirect_url, Url::parse(redirect_url)) {
+            (None, _) => true,
+            (Some(pasted), Ok(url)) => *pasted == url[..Position::AfterPath],
+            (Some(_), Err(_)) => false,
+        }
+    }
+}
+
+
+/// Parses a redirect URL, as pasted from the address bar or received by the
+/// callback server, or a bare code. A redirect carrying LinkedIn's `error`,
+/// e.g. when the member cancelled the sign in, gives `AuthError::OAuth`, one
+/// with neither a code nor an error `AuthError::MissingCode`.
+pub fn parse_redirect(input: &str) -> Result<RedirectParams, AuthError> {
+    let url = match Url::parse(input) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        _ => return Ok(RedirectParams { code: input.to_string(), state: None, redirect_url: None }),
+    };
+
+    let param = |name: &str| url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned());
+
+    match (param("code"), param("error")) {
+        (Some(code), _) => Ok(RedirectParams {
+            code,
+            state: param("state"),
+            redirect_url: Some(url[..Position::AfterPath].to_string()),
+        }),
+        (None, Some(error)) => {
+            Err(AuthError::OAuth { error, description: param("error_description").unwrap_or_default() })
+        },
+        (None, None) => Err(AuthError::MissingCode),
+    }
+}
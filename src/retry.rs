@@ -0,0 +1,104 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rand::{Rng, thread_rng};
+use reqwest::{Error as ReqError, Response, StatusCode};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use tracing::debug;
+
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+
+/// Which failures `send_with_retries` repeats a request after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RetryOn {
+    /// Connection failures, timeouts, 429 and 5xx responses.
+    Transient,
+    /// Connection failures only, for requests that must not reach LinkedIn
+    /// twice such as the exchange of a single-use authorization code.
+    Connect,
+}
+
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+
+// Only the delta-seconds form of Retry-After is supported.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+
+/// The delay before retry `attempt + 1`: 0.5s doubling with each attempt up
+/// to 30s or, with `jitter`, a random delay between zero and that cap.
+pub fn backoff(attempt: u32, jitter: bool) -> Duration {
+    let cap = BASE_DELAY.checked_mul(2u32.saturating_pow(attempt))
+        .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY));
+    match jitter {
+        true => cap.mul_f64(thread_rng().gen::<f64>()),
+        false => cap,
+    }
+}
+
+
+// How long to wait before repeating a request, `None` if it must not be
+// repeated. Other 4xx are returned right away since repeating them cannot
+// succeed, and so is a response asking to come back after more than the
+// longest backoff.
+fn response_delay(status: StatusCode, headers: &HeaderMap, attempt: u32, jitter: bool) -> Option<Duration> {
+    if !is_transient(status) {
+        return None;
+    }
+    match retry_after(headers) {
+        Some(delay) if delay > MAX_DELAY => None,
+        Some(delay) => Some(delay),
+        None => Some(backoff(attempt, jitter)),
+    }
+}
+
+
+// A timed out request may still have been processed, only a failed connect
+// certainly was not.
+fn error_delay(err: &ReqError, retry_on: RetryOn, attempt: u32, jitter: bool) -> Option<Duration> {
+    let retry = match retry_on {
+        RetryOn::Transient => err.is_connect() || err.is_timeout(),
+        RetryOn::Connect => err.is_connect(),
+    };
+    match retry {
+        true => Some(backoff(attempt, jitter)),
+        false => None,
+    }
+}
+
+
+/// Sends the request built by `send`, retrying up to `max_retries` times on
+/// the failures `retry_on` names, see `backoff` for the delays.
+pub(crate) async fn send_with_retries<F, R>(send: F, retry_on: RetryOn, max_retries: u32, jitter: bool)
+                                               -> Result<Response, ReqError>
+    where F: Fn() -> R, R: Future<Output = Result<Response, ReqError>> {
+
+    let mut attempt = 0;
+    loop {
+        let result = send().await;
+        let delay = match &result {
+            Ok(_) if retry_on == RetryOn::Connect => None,
+            Ok(response) => response_delay(response.status(), response.headers(), attempt, jitter),
+            Err(err) => error_delay(err, retry_on, attempt, jitter),
+        };
+
+        match delay {
+            Some(delay) if attempt < max_retries => {
+                attempt += 1;
+                debug!("transient failure, retry {} of {} in {:?}", attempt, max_retries, delay);
+                tokio::time::sleep(delay).await;
+            },
+            _ => return result,
+        }
+    }
+}
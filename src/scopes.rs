@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Permissions LinkedIn is known to grant. LinkedIn matches them
+/// case-sensitively, `normalize_scopes` fixes their casing.
+pub const KNOWN_SCOPES: &[&str] = &[
+    "r_ads", "r_ads_reporting", "rw_ads",
+    "r_liteprofile", "r_basicprofile", "r_emailaddress",
+    "w_member_social", "r_member_social",
+    "r_organization_social", "w_organization_social",
+    "rw_organization_admin", "r_1st_connections_size",
+    "openid", "profile", "email",
+];
+
+
+/// `KNOWN_SCOPES` grouped for the `--interactive-scopes` menu.
+pub const SCOPE_CATEGORIES: &[(&str, &[&str])] = &[
+    ("Advertising", &["r_ads", "r_ads_reporting", "rw_ads"]),
+    ("Profile", &["r_liteprofile", "r_basicprofile", "r_emailaddress", "r_1st_connections_size"]),
+    ("Sign In with LinkedIn", &["openid", "profile", "email"]),
+    ("Member posts", &["w_member_social", "r_member_social"]),
+    ("Organizations", &["r_organization_social", "w_organization_social", "rw_organization_admin"]),
+];
+
+
+/// Curated scope lists selected with `--scope-set`.
+pub const SCOPE_SETS: &[(&str, &[&str])] = &[
+    ("ads", &["r_ads", "r_ads_reporting"]),
+    ("profile", &["openid", "profile", "email"]),
+    ("social", &["w_member_social", "r_liteprofile", "w_organization_social", "r_organization_social"]),
+];
+
+
+/// LinkedIn developer products and the permissions they provision, which an
+/// `--app-products` file may name instead of listing the permissions.
+pub const PRODUCTS: &[(&str, &[&str])] = &[
+    ("Sign In with LinkedIn using OpenID Connect", &["openid", "profile", "email"]),
+    ("Sign In with LinkedIn", &["r_liteprofile", "r_emailaddress"]),
+    ("Share on LinkedIn", &["w_member_social"]),
+    ("Advertising API", &["r_ads", "r_ads_reporting", "rw_ads", "rw_organization_admin"]),
+    ("Community Management API", &["r_organization_social", "w_organization_social", "rw_organization_admin",
+                                   "r_member_social"]),
+];
+
+
+/// Requested permissions LinkedIn does not know, or, when the app's products
+/// were given, ones the app is not `provisioned` for.
+#[derive(Debug)]
+pub struct UnknownScopes {
+    pub scopes: Vec<String>,
+    pub provisioned: Option<Vec<String>>,
+}
+
+impl fmt::Display for UnknownScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.provisioned {
+            Some(provisioned) => write!(f, "the app is not provisioned for: {}, add the products granting \
+                                            them in the LinkedIn developer portal. The app has: {}",
+                                        self.scopes.join(", "), provisioned.join(", ")),
+            None => write!(f, "unknown permissions: {}. Valid permissions are: {}",
+                           self.scopes.join(", "), KNOWN_SCOPES.join(", ")),
+        }
+    }
+}
+
+impl Error for UnknownScopes {}
+
+
+#[derive(Debug)]
+pub struct ScopesFileError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ScopesFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot read the permissions file '{}': {}", self.path, self.reason)
+    }
+}
+
+impl Error for ScopesFileError {}
+
+
+pub fn validate_scopes(scopes: &[&str]) -> Result<(), UnknownScopes> {
+    let unknown: Vec<String> = scopes.iter()
+        .filter(|scope| !KNOWN_SCOPES.contains(scope))
+        .map(|scope| scope.to_string())
+        .collect();
+
+    match unknown.is_empty() {
+        true => Ok(()),
+        false => Err(UnknownScopes { scopes: unknown, provisioned: None }),
+    }
+}
+
+
+/// Checks the known scopes among `scopes` against the permissions the app
+/// is `provisioned` for, see `load_app_products`. Unknown scopes are left to
+/// `validate_scopes`.
+pub fn validate_provisioned_scopes(scopes: &[&str], provisioned: &[String]) -> Result<(), UnknownScopes> {
+    let missing: Vec<String> = scopes.iter()
+        .filter(|scope| KNOWN_SCOPES.contains(scope) && !provisioned.iter().any(|allowed| allowed == *scope))
+        .map(|scope| scope.to_string())
+        .collect();
+
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(UnknownScopes { scopes: missing, provisioned: Some(provisioned.to_vec()) }),
+    }
+}
+
+
+pub fn scope_set(name: &str) -> Option<&'static [&'static str]> {
+    SCOPE_SETS.iter().find(|(set, _)| *set == name).map(|(_, scopes)| *scopes)
+}
+
+
+/// Reads a newline or comma separated list of scopes, skipping blank lines
+/// and `#` comments.
+pub fn load_scopes_file(path: &Path) -> Result<Vec<String>, ScopesFileError> {
+    let contents = fs::read_to_string(path).map_err(|err| ScopesFileError {
+        path: path.display().to_string(),
+        reason: err.to_string(),
+    })?;
+
+    Ok(contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|scope| !scope.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+
+/// Reads an `--app-products` file, a list like `load_scopes_file` reads
+/// where entries naming one of the `PRODUCTS` stand for its permissions.
+/// Any other entry must be one of the `KNOWN_SCOPES`, so a misspelled product
+/// is reported with its line instead of becoming a bogus scope.
+pub fn load_app_products(path: &Path) -> Result<Vec<String>, ScopesFileError> {
+    let invalid = |reason: String| ScopesFileError { path: path.display().to_string(), reason };
+    let contents = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+
+    let mut scopes = Vec::new();
+    for (number, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().starts_with('#')) {
+        for entry in line.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let product = PRODUCTS.iter().find(|(product, _)| product.eq_ignore_ascii_case(entry));
+            if let Some((_, provisioned)) = product {
+                scopes.extend(provisioned.iter().map(|scope| scope.to_string()));
+            } else if let Some(known) = KNOWN_SCOPES.iter().find(|known| known.eq_ignore_ascii_case(entry)) {
+                scopes.push(known.to_string());
+            } else {
+                return Err(invalid(format!("line {}: '{}' is neither a LinkedIn product nor a known permission",
+                                           number + 1, entry)));
+            }
+        }
+    }
+    dedup_scopes(&mut scopes);
+    Ok(scopes)
+}
+
+
+/// Splits each value on commas and whitespace, so `"r_ads,r_ads_reporting"`
+/// copied from LinkedIn's docs gives two scopes. Repeated scopes are dropped.
+pub fn split_scopes<'a, I>(values: I) -> Vec<String>
+    where I: IntoIterator<Item = &'a str> {
+
+    let mut scopes: Vec<String> = values.into_iter()
+        .flat_map(|value| value.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|scope| !scope.is_empty())
+        .map(str::to_string)
+        .collect();
+    dedup_scopes(&mut scopes);
+    scopes
+}
+
+
+/// Drops repeated scopes, keeping the first occurrence of each.
+pub fn dedup_scopes(scopes: &mut Vec<String>) {
+    let mut seen = Vec::new();
+    scopes.retain(|scope| match seen.contains(scope) {
+        true => false,
+        false => {
+            seen.push(scope.clone());
+            true
+        },
+    });
+}
+
+
+/// Trims every scope, drops empty ones, gives known scopes their canonical
+/// lowercase spelling and then drops repeats, so scopes merged from flags,
+/// files, presets and the config file make a clean `scope` parameter.
+pub fn normalize_scopes(scopes: &mut Vec<String>) {
+    for scope in scopes.iter_mut() {
+        let trimmed = scope.trim();
+        *scope = match KNOWN_SCOPES.iter().find(|known| known.eq_ignore_ascii_case(trimmed)) {
+            Some(known) => known.to_string(),
+            None => trimmed.to_string(),
+        };
+    }
+    scopes.retain(|scope| !scope.is_empty());
+    dedup_scopes(scopes);
+}
+
+
+/// The requested scopes missing from the `scope` LinkedIn granted, which
+/// happens when the app is not approved for a product.
+pub fn missing_scopes<'a>(requested: &'a [String], granted: &str) -> Vec<&'a str> {
+    let granted = split_scopes([granted]);
+    requested.iter()
+        .filter(|scope| !granted.contains(scope))
+        .map(String::as_str)
+        .collect()
+}
@@ -0,0 +1,49 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::files::write_private_file;
+
+
+/// What a run generating the authorization URL leaves for a later run that
+/// exchanges the code: the `state` and, when used, the PKCE verifier and the
+/// OpenID Connect nonce.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Session {
+    pub client_id: String,
+    pub csrf: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_verifier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+impl Session {
+    /// `~/.config/linkedin-auth/session.json` or its platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("linkedin-auth").join("session.json"))
+    }
+
+    /// Loads the session at `path`, `None` when there is none.
+    pub fn load(path: &Path) -> io::Result<Option<Session>> {
+        match fs::read(path) {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_private_file(path, &serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Deletes the session at `path`, a missing file is not an error.
+    pub fn remove(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::TokenResponse;
+
+/// Placeholders `render_token` knows, written as `{name}` in a template.
+pub const PLACEHOLDERS: &[&str] = &["access_token", "refresh_token", "expires_in", "scope"];
+
+
+#[derive(Debug)]
+pub struct UnknownPlaceholder(pub String);
+
+impl fmt::Display for UnknownPlaceholder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown placeholder '{{{}}}' in the template, expected one of: {}",
+               self.0, PLACEHOLDERS.join(", "))
+    }
+}
+
+impl Error for UnknownPlaceholder {}
+
+
+fn placeholder_value(token: &TokenResponse, name: &str) -> Option<String> {
+    match name {
+        "access_token" => Some(token.access_token.clone()),
+        "refresh_token" => Some(token.refresh_token.clone().unwrap_or_default()),
+        "expires_in" => Some(token.expires_in.map(|seconds| seconds.to_string()).unwrap_or_default()),
+        "scope" => Some(token.scope.clone().unwrap_or_default()),
+        _ => None,
+    }
+}
+
+
+// A `{` without a closing `}` is kept as is.
+fn substitute(template: &str, value_of: impl Fn(&str) -> Option<String>)
+                  -> Result<String, UnknownPlaceholder> {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = &rest[start + 1..end];
+        let value = value_of(name).ok_or_else(|| UnknownPlaceholder(name.to_string()))?;
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+
+/// Substitutes the `{name}` placeholders of `template` with the fields of
+/// `token`, those missing from the response become empty.
+pub fn render_token(template: &str, token: &TokenResponse) -> Result<String, UnknownPlaceholder> {
+    substitute(template, |name| placeholder_value(token, name))
+}
+
+
+/// Checks that `template` only uses known placeholders, before there is a
+/// token to render.
+pub fn check_template(template: &str) -> Result<(), UnknownPlaceholder> {
+    substitute(template, |name| PLACEHOLDERS.contains(&name).then(String::new)).map(|_| ())
+}
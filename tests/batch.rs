@@ -0,0 +1,98 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{self, Command, Stdio};
+
+use httpmock::prelude::*;
+
+
+#[test]
+fn every_entry_gets_a_token_file_and_a_summary_row() {
+    let server = MockServer::start();
+    for (code, token) in [("AQQads", "AQVadstoken1234"), ("AQQsocial", "AQVsocialtoken5678")] {
+        server.mock(|when, then| {
+            when.method(POST).path("/accessToken").x_www_form_urlencoded_tuple("code", code);
+            then.status(200).body(format!(r#"{{"access_token": "{}", "expires_in": 5183999}}"#, token));
+        });
+    }
+
+    let dir = env::temp_dir().join(format!("lin_auth_batch_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("linkedin-auth.toml");
+    fs::write(&config, r#"
+        [linkedin]
+        client_id = "client-id"
+        client_secret = "client-secret"
+
+        [[batch]]
+        name = "ads"
+        scopes = ["r_ads", "r_ads_reporting"]
+
+        [[batch]]
+        name = "social"
+        scopes = ["w_member_social"]
+        output = "social-token.json"
+    "#).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["batch", "--config", config.to_str().unwrap(), "--no-server"])
+        .args(["--output-dir", dir.to_str().unwrap(), "--token-url", &server.url("/accessToken")])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"AQQads\nAQQsocial\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(fs::read_to_string(dir.join("ads.json")).unwrap().contains("AQVadstoken1234"));
+    assert!(fs::read_to_string(dir.join("social-token.json")).unwrap().contains("AQVsocialtoken5678"));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let summary: Vec<&str> = stdout.lines().skip_while(|line| !line.starts_with("NAME")).collect();
+    assert_eq!(summary.len(), 3, "{}", stdout);
+    assert!(summary[1].starts_with("ads ") && summary[1].contains("AQVa...1234"));
+    assert!(summary[2].starts_with("social ") && summary[2].contains("social-token.json"));
+    assert!(!stdout.contains("AQVadstoken1234"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+
+#[test]
+fn unknown_scope_of_a_later_entry_fails_before_any_exchange() {
+    let server = MockServer::start();
+    let endpoint = server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(r#"{"access_token": "AQVtoken"}"#);
+    });
+
+    let dir = env::temp_dir().join(format!("lin_auth_batch_scopes_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("linkedin-auth.toml");
+    fs::write(&config, r#"
+        [linkedin]
+        client_id = "client-id"
+        client_secret = "client-secret"
+
+        [[batch]]
+        name = "ads"
+        scopes = [" R_ADS "]
+
+        [[batch]]
+        name = "social"
+        scopes = ["w_member_socail"]
+    "#).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["batch", "--config", config.to_str().unwrap(), "--no-server"])
+        .args(["--output-dir", dir.to_str().unwrap(), "--token-url", &server.url("/accessToken")])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("w_member_socail"));
+    assert_eq!(endpoint.hits(), 0);
+}
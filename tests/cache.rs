@@ -0,0 +1,31 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lin_auth::TokenResponse;
+use lin_auth::cache::TokenCache;
+
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+
+#[test]
+fn expiry_is_read_from_the_stored_token() {
+    let path = env::temp_dir().join("lin_auth_cache_expiry.json");
+    let mut cache = TokenCache::load(&path).unwrap();
+
+    let fresh: TokenResponse = serde_json::from_str(r#"{"access_token": "AQVfresh", "expires_in": 3600}"#).unwrap();
+    cache.insert("fresh", &fresh);
+    let expired: TokenResponse = serde_json::from_str(
+        &format!(r#"{{"access_token": "AQVexpired", "expires_in": 3600, "expires_at": {}}}"#, unix_now() - 60),
+    ).unwrap();
+    cache.insert("expired", &expired);
+
+    let token = cache.get("fresh").unwrap();
+    assert!(token.expires_at.is_some_and(|expires_at| expires_at > unix_now() + 3500));
+    assert!(token.expires_in.is_some_and(|expires_in| expires_in <= 3600));
+
+    assert!(cache.get("expired").is_none());
+    assert!(cache.entry("expired").unwrap().is_expired());
+}
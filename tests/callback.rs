@@ -0,0 +1,130 @@
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Shutdown, TcpListener, TcpStream};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use lin_auth::AuthError;
+use lin_auth::callback::{CallbackAddress, CallbackServer, PortInUse, TooManyCallbackRequests, callback_address,
+                         listen_address, wait_for_callback};
+
+
+// Sends a single request to the callback server, retrying until it listens.
+fn request(port: u16, target: &str) -> String {
+    let mut stream = loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => break stream,
+            Err(_) => thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+
+#[test]
+fn favicon_request_does_not_end_the_wait() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let address = CallbackAddress { ip: Ipv4Addr::LOCALHOST.into(), port, tls: false };
+        wait_for_callback(address, "csrf", Some(Duration::from_secs(10))).unwrap()
+    });
+
+    assert!(request(port, "/favicon.ico").starts_with("HTTP/1.1 404"));
+    assert!(request(port, "/callback?code=AQQcode&state=other").starts_with("HTTP/1.1 404"));
+    assert!(request(port, "/callback?code=AQQcode&state=csrf").starts_with("HTTP/1.1 200"));
+    assert_eq!(server.join().unwrap(), "AQQcode");
+}
+
+
+#[test]
+fn error_redirect_ends_the_wait() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let address = CallbackAddress { ip: Ipv4Addr::LOCALHOST.into(), port, tls: false };
+        wait_for_callback(address, "csrf", Some(Duration::from_secs(10))).unwrap_err().to_string()
+    });
+
+    assert!(request(port, "/callback?error=user_cancelled_login&state=other").starts_with("HTTP/1.1 404"));
+    let response = request(port, "/callback?error=user_cancelled_login&error_description=cancelled&state=csrf");
+    assert!(response.starts_with("HTTP/1.1 200") && response.contains("Authorization failed."));
+    assert_eq!(server.join().unwrap(), AuthError::OAuth {
+        error: "user_cancelled_login".to_string(),
+        description: "cancelled".to_string(),
+    }.to_string());
+}
+
+
+#[test]
+fn server_gives_up_after_too_many_stray_requests() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = CallbackServer::bind(CallbackAddress { ip: Ipv4Addr::LOCALHOST.into(), port, tls: false })
+        .unwrap()
+        .with_max_requests(3);
+    let waiting = thread::spawn(move || {
+        let err = server.wait("csrf", Some(Duration::from_secs(10))).unwrap_err();
+        err.downcast_ref::<TooManyCallbackRequests>().map(|err| err.0)
+    });
+
+    for _ in 0..3 {
+        assert!(request(port, "/favicon.ico").starts_with("HTTP/1.1 404"));
+    }
+    assert_eq!(waiting.join().unwrap(), Some(3));
+}
+
+
+#[test]
+fn failed_connections_count_toward_the_limit() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = CallbackServer::bind(CallbackAddress { ip: Ipv4Addr::LOCALHOST.into(), port, tls: false })
+        .unwrap()
+        .with_max_requests(2);
+    let waiting = thread::spawn(move || {
+        let err = server.wait("csrf", Some(Duration::from_secs(10))).unwrap_err();
+        err.downcast_ref::<TooManyCallbackRequests>().map(|err| err.0)
+    });
+
+    // Closed without a request line, handle_request fails on them.
+    for _ in 0..2 {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.shutdown(Shutdown::Both).unwrap();
+    }
+    assert_eq!(waiting.join().unwrap(), Some(2));
+}
+
+
+#[test]
+fn zero_callback_requests_are_rejected() {
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "77abcdefgh1234", "--dry-run", "--max-callback-requests", "0"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("at least 1"));
+}
+
+
+#[test]
+fn taken_port_is_reported_up_front() {
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+
+    let err = CallbackServer::bind(CallbackAddress { ip: Ipv4Addr::LOCALHOST.into(), port, tls: false }).unwrap_err();
+    assert!(matches!(err.downcast_ref::<PortInUse>(), Some(PortInUse(taken)) if *taken == port));
+}
+
+
+#[test]
+fn listen_address_follows_the_redirect_url() {
+    let address = callback_address("http://0.0.0.0:8000/callback").unwrap();
+    assert_eq!((address.ip, address.port, address.tls), (Ipv4Addr::UNSPECIFIED.into(), 8000, false));
+    assert!(callback_address("https://example.com/callback").is_none());
+
+    let address = listen_address("https://auth.example.com/callback", "127.0.0.1:9000").unwrap();
+    assert_eq!((address.ip, address.port, address.tls), (Ipv4Addr::LOCALHOST.into(), 9000, true));
+    assert!(listen_address("https://auth.example.com/callback", "localhost").is_err());
+}
@@ -0,0 +1,65 @@
+use std::env;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use httpmock::prelude::*;
+
+
+fn dry_run(extra: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--dry-run", "--session-file", env::temp_dir().join("lin_auth_session_client_id").to_str().unwrap()])
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+
+#[test]
+fn malformed_client_id_warns_unless_strict() {
+    let output = dry_run(&["--client-id", "my-app"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Warning: 'my-app' does not look like a LinkedIn client id"), "{}", stderr);
+
+    let output = dry_run(&["--client-id", "my-app", "--strict"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+}
+
+
+#[test]
+fn pasted_whitespace_is_trimmed() {
+    let output = dry_run(&["--client-id", " 78abcd1234efgh\n", "--strict"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8(output.stdout).unwrap().contains("client_id=78abcd1234efgh&"));
+}
+
+
+#[test]
+fn credentials_are_read_from_the_environment() {
+    let server = MockServer::start();
+    let endpoint = server.mock(|when, then| {
+        when.method(POST)
+            .path("/accessToken")
+            .x_www_form_urlencoded_tuple("client_id", "78abcd1234efgh")
+            .x_www_form_urlencoded_tuple("client_secret", "s3cr3t");
+        then.status(200).body(r#"{"access_token": "AQVtoken"}"#);
+    });
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--no-server", "--no-cache", "--token-url", &server.url("/accessToken")])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_client_env").to_str().unwrap()])
+        .env("LINKEDIN_CLIENT_ID", "78abcd1234efgh")
+        .env("LINKEDIN_CLIENT_SECRET", "s3cr3t")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"AQQcode\n").unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    endpoint.assert();
+}
@@ -0,0 +1,36 @@
+#![allow(dead_code)]
+
+use std::env;
+use std::process::{self, Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use httpmock::prelude::*;
+
+
+// Tests run in parallel, each run gets a session file of its own.
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+
+pub fn token_server(body: &str) -> MockServer {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(body);
+    });
+    server
+}
+
+
+pub fn run_exchange(token_url: &str, extra_args: &[&str]) -> Output {
+    let session_file = env::temp_dir()
+        .join(format!("lin_auth_session_{}_{}", process::id(), RUNS.fetch_add(1, Ordering::Relaxed)));
+
+    Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "78abcd1234efgh", "--client-secret", "client-secret", "--no-cache",
+               "--code", "AQQcode", "--csrf", "csrf", "--state", "csrf"])
+        .args(["--session-file", session_file.to_str().unwrap()])
+        .args(["--token-url", token_url])
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
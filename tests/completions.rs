@@ -0,0 +1,16 @@
+use std::process::Command;
+
+
+#[test]
+fn bash_completions_list_subcommands() {
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["completions", "bash"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(!script.is_empty());
+    assert!(script.contains("refresh"));
+    assert!(script.contains("completions"));
+}
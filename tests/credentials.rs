@@ -0,0 +1,71 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use lin_auth::credentials::Credentials;
+#[cfg(unix)]
+use lin_auth::credentials::InsecurePermissions;
+
+
+fn credentials(client_id: &str, client_secret: &str) -> Credentials {
+    Credentials { client_id: Some(client_id.to_string()), client_secret: Some(client_secret.to_string()) }
+}
+
+
+fn private_file(name: &str, contents: &str, mode: u32) -> PathBuf {
+    let path = env::temp_dir().join(format!("lin_auth_credentials_{}", name));
+    fs::write(&path, contents).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    path
+}
+
+
+#[test]
+fn toml_and_key_value_lines_are_read() {
+    let toml = "client_id = \"78abcd1234efgh\"\nclient_secret = \"s3cr3t\"\n";
+    assert_eq!(Credentials::parse(toml).unwrap(), credentials("78abcd1234efgh", "s3cr3t"));
+
+    let dotenv = "# exported from the developer portal\nLINKEDIN_CLIENT_ID=78abcd1234efgh\n\nCLIENT_SECRET='s3cr3t'\n";
+    assert_eq!(Credentials::parse(dotenv).unwrap(), credentials("78abcd1234efgh", "s3cr3t"));
+
+    assert!(Credentials::parse("client_id=78abcd1234efgh\nredirect_url=https://localhost").is_err());
+    assert!(Credentials::parse("just a secret").is_err());
+}
+
+
+#[test]
+fn unparsable_lines_are_reported_by_number_only() {
+    let err = Credentials::parse("client_id=78abcd1234efgh\n\nWJr6s3cr3tXk").unwrap_err();
+    assert_eq!(err, "line 3 is not a KEY=VALUE line");
+
+    let err = Credentials::parse("client_id=78abcd1234efgh\nWJr6s3cr3tXk=1").unwrap_err();
+    assert!(err.starts_with("line 2 has an unknown key"), "{}", err);
+    assert!(!err.to_ascii_lowercase().contains("wjr6s3cr3txk"), "{}", err);
+}
+
+
+#[test]
+fn private_file_is_loaded() {
+    let path = private_file("private", "client_id=78abcd1234efgh\nclient_secret=s3cr3t\n", 0o600);
+
+    assert_eq!(Credentials::load(&path).unwrap(), credentials("78abcd1234efgh", "s3cr3t"));
+    fs::remove_file(path).unwrap();
+}
+
+
+#[cfg(unix)]
+#[test]
+fn readable_by_others_is_refused() {
+    let path = private_file("open", "client_id=78abcd1234efgh\nclient_secret=s3cr3t\n", 0o644);
+
+    let err = Credentials::load(&path).unwrap_err();
+    let err = err.downcast_ref::<InsecurePermissions>().unwrap();
+    assert_eq!(err.mode, 0o644);
+    fs::remove_file(path).unwrap();
+}
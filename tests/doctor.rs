@@ -0,0 +1,62 @@
+use std::process::{Command, Output};
+
+use httpmock::prelude::*;
+
+
+fn doctor(server: &MockServer, client_id: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["doctor", "--client-id", client_id, "--client-secret", "client-secret",
+               "--redirect-url", "http://localhost:8000/callback", "--max-retries", "0"])
+        .args(["--auth-url", &server.url("/authorization"), "--token-url", &server.url("/accessToken")])
+        .output()
+        .unwrap()
+}
+
+
+#[test]
+fn rejected_code_means_the_credentials_are_accepted() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(400).body(r#"{"error": "invalid_request", "error_description": "Unable to retrieve"}"#);
+    });
+
+    let output = doctor(&server, "78abcd1234efgh");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{}", stdout);
+    assert_eq!(stdout.matches('\u{2705}').count(), 4);
+}
+
+
+#[test]
+fn invalid_client_fails_the_credentials_check() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(401).body(r#"{"error": "invalid_client", "error_description": "Bad credentials"}"#);
+    });
+
+    let output = doctor(&server, "not a client id");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stdout.contains("\u{274c} Client id"));
+    assert!(stdout.contains("\u{274c} Credentials"));
+}
+
+
+#[test]
+fn client_id_rules_match_the_main_flow() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(400).body(r#"{"error": "invalid_request", "error_description": "Unable to retrieve"}"#);
+    });
+
+    // Longer than the ids LinkedIn issues today, which the main flow accepts.
+    let output = doctor(&server, "78abcd1234efgh5678ijkl9012");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.contains("\u{2705} Client id"), "{}", stdout);
+}
@@ -0,0 +1,42 @@
+use std::env;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+use lin_auth::dotenv::write_dotenv;
+
+
+#[test]
+fn other_lines_are_kept() {
+    let path = env::temp_dir().join("lin_auth_dotenv_kept");
+    fs::write(&path, "PORT=8080\nLINKEDIN_ACCESS_TOKEN=old\n").unwrap();
+
+    write_dotenv(&path, &[("LINKEDIN_ACCESS_TOKEN", "AQVnew".to_string())]).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "PORT=8080\nLINKEDIN_ACCESS_TOKEN=\"AQVnew\"\n");
+    fs::remove_file(path).unwrap();
+}
+
+
+#[cfg(unix)]
+#[test]
+fn existing_file_keeps_its_mode() {
+    let path = env::temp_dir().join("lin_auth_dotenv_mode");
+    fs::write(&path, "PORT=8080\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+    write_dotenv(&path, &[("LINKEDIN_ACCESS_TOKEN", "AQVnew".to_string())]).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+    fs::remove_file(path).unwrap();
+}
+
+
+#[cfg(unix)]
+#[test]
+fn new_file_is_private() {
+    let path = env::temp_dir().join("lin_auth_dotenv_new");
+    let _ = fs::remove_file(&path);
+
+    write_dotenv(&path, &[("LINKEDIN_ACCESS_TOKEN", "AQVnew".to_string())]).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use httpmock::prelude::*;
+
+use common::run_exchange;
+
+
+#[test]
+fn user_agent_and_extra_headers_are_sent() {
+    let server = MockServer::start();
+    let endpoint = server.mock(|when, then| {
+        when.method(POST)
+            .path("/accessToken")
+            .header("user-agent", "gateway-friendly/1.0")
+            .header("x-request-source", "ci");
+        then.status(200).body(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+    });
+
+    let output = run_exchange(&server.url("/accessToken"), &["--quiet", "--user-agent", "gateway-friendly/1.0",
+                                                            "--header", "X-Request-Source: ci"]);
+
+    assert!(output.status.success());
+    endpoint.assert();
+}
+
+
+#[test]
+fn authorization_header_is_rejected() {
+    let server = MockServer::start();
+
+    let output = run_exchange(&server.url("/accessToken"), &["--quiet", "--header", "Authorization: Bearer AQVtoken"]);
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("authorization header"));
+}
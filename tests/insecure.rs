@@ -0,0 +1,23 @@
+mod common;
+
+use common::{run_exchange, token_server};
+
+
+#[test]
+fn invalid_certs_are_refused_for_linkedin() {
+    let output = run_exchange("https://www.linkedin.com/oauth/v2/accessToken", &["--danger-accept-invalid-certs"]);
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("refused for www.linkedin.com"));
+}
+
+
+#[test]
+fn invalid_certs_are_accepted_loudly_for_test_servers() {
+    let server = token_server(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+
+    let output = run_exchange(&server.url("/accessToken"), &["--quiet", "--danger-accept-invalid-certs"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Warning: TLS certificates are NOT verified"));
+}
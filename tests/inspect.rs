@@ -0,0 +1,65 @@
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::encode_config;
+use httpmock::prelude::*;
+
+
+fn inspect(extra: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .arg("inspect")
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+
+#[test]
+fn id_token_claims_are_decoded_offline() {
+    let exp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 7230;
+    let claims = format!(r#"{{"sub": "782bbtaQ", "email": "jane@example.com", "exp": {}}}"#, exp);
+    let id_token = format!("eyJhbGciOiJSUzI1NiJ9.{}.c2lnbmF0dXJl", encode_config(claims, base64::URL_SAFE_NO_PAD));
+
+    let output = inspect(&["--token", &id_token, "--offline"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("    sub: 782bbtaQ\n") && stdout.contains("    email: jane@example.com\n"), "{}", stdout);
+    assert!(stdout.contains("    exp: expires at ") && stdout.contains(" / in 2 hours"), "{}", stdout);
+}
+
+
+#[test]
+fn opaque_token_is_checked_against_the_profile_endpoints() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/v2/userinfo");
+        then.status(403).body(r#"{"message": "Not enough permissions to access: userinfo.GET.NO_VERSION"}"#);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/v2/me").header("authorization", "Bearer AQVtoken");
+        then.status(200).body(r#"{"id": "782bbtaQ", "localizedFirstName": "Jane", "localizedLastName": "Doe"}"#);
+    });
+
+    let output = inspect(&["--token", "AQVtoken", "--api-url", &server.url("/v2/")]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("The access key works for Jane Doe (782bbtaQ)."), "{}", stdout);
+    assert!(stdout.contains("It grants r_liteprofile"), "{}", stdout);
+}
+
+
+#[test]
+fn rejected_token_exits_with_3() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/v2/userinfo");
+        then.status(401).body(r#"{"message": "Invalid access token"}"#);
+    });
+
+    let output = inspect(&["--token", "AQVexpired", "--api-url", &server.url("/v2/")]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("LinkedIn rejected the access token"));
+}
@@ -0,0 +1,33 @@
+#![cfg(unix)]
+
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+
+#[test]
+fn ctrl_c_while_waiting_for_the_code_cancels() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-cache",
+               "--no-server", "-p", "r_ads", "-r", "http://localhost:8000/callback"])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_interrupt").to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.contains("pass it here") {
+        line.clear();
+        assert_ne!(stdout.read_line(&mut line).unwrap(), 0, "the prompt was never printed");
+    }
+
+    let killed = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    assert!(killed.success());
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Cancelled."));
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use std::process::Output;
+
+use common::{run_exchange, token_server};
+
+
+fn exchange(body: &str) -> Output {
+    let server = token_server(body);
+    run_exchange(&server.url("/accessToken"), &["--offline"])
+}
+
+
+#[test]
+fn missing_refresh_token_is_warned_about() {
+    let output = exchange(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Warning: --offline was given but LinkedIn returned no refresh token"), "{}", stderr);
+}
+
+
+#[test]
+fn refresh_token_passes_quietly() {
+    let output = exchange(r#"{"access_token": "AQVtoken", "token_type": "Bearer", "refresh_token": "AQXrefresh"}"#);
+
+    assert!(output.status.success());
+    assert!(!String::from_utf8(output.stderr).unwrap().contains("refresh token"));
+}
@@ -0,0 +1,57 @@
+use std::env;
+use std::process::{Command, Output};
+
+use httpmock::prelude::*;
+
+
+fn verify(server: &MockServer, organization: &str) -> Output {
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/me").header("Authorization", "Bearer AQVtoken");
+        then.status(200).body(r#"{"id": "abc123", "localizedFirstName": "Ada", "localizedLastName": "Lovelace"}"#);
+    });
+
+    Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-cache",
+               "-p", "w_organization_social", "--code", "AQQcode", "--csrf", "csrf"])
+        .args(["--verify", "--organization", organization])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_organization").to_str().unwrap()])
+        .args(["--token-url", &server.url("/accessToken"), "--api-url", &server.url("/")])
+        .output()
+        .unwrap()
+}
+
+
+#[test]
+fn approved_role_passes_the_check() {
+    let server = MockServer::start();
+    let acls = server.mock(|when, then| {
+        when.method(GET).path("/organizationAcls").query_param("q", "roleAssignee");
+        then.status(200).body(r#"{"elements": [{"organization": "urn:li:organization:2414183",
+                                                "role": "ADMINISTRATOR", "state": "APPROVED"}]}"#);
+    });
+
+    let output = verify(&server, "2414183");
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    acls.assert();
+}
+
+
+#[test]
+fn rejected_organization_call_is_told_apart() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/organizationAcls");
+        then.status(403).body(r#"{"message": "Not enough permissions to access: organizationAcls"}"#);
+    });
+
+    let output = verify(&server, "urn:li:organization:2414183");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr.contains("works for member calls but not for urn:li:organization:2414183"), "{}", stderr);
+}
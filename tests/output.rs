@@ -0,0 +1,22 @@
+use lin_auth::output::{Reporter, describe_duration};
+
+
+#[test]
+fn durations_use_the_largest_whole_unit() {
+    assert_eq!(describe_duration(1), "1 second");
+    assert_eq!(describe_duration(59), "59 seconds");
+    assert_eq!(describe_duration(7230), "2 hours");
+    assert_eq!(describe_duration(60 * 86400), "60 days");
+}
+
+
+#[test]
+fn json_is_compact_unless_pretty() {
+    let value = serde_json::json!({ "authorization_url": "https://example.com" });
+
+    let compact = Reporter::default().to_json(&value).unwrap();
+    let pretty = Reporter { pretty: true, ..Reporter::default() }.to_json(&value).unwrap();
+
+    assert_eq!(compact, r#"{"authorization_url":"https://example.com"}"#);
+    assert_eq!(pretty, "{\n  \"authorization_url\": \"https://example.com\"\n}");
+}
@@ -0,0 +1,121 @@
+use std::env;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::process::{self, Command, Stdio};
+
+use httpmock::prelude::*;
+
+use lin_auth::prompt::{EmptyCode, read_code, read_code_file};
+
+
+#[test]
+fn empty_lines_are_prompted_again() {
+    let mut reprompts = 0;
+    let pasted = read_code(&mut Cursor::new("\n  \nAQQcode\n"), || reprompts += 1).unwrap();
+
+    assert_eq!(pasted.code, "AQQcode");
+    assert_eq!(pasted.state, None);
+    assert_eq!(reprompts, 2);
+}
+
+
+#[test]
+fn pasted_code_is_trimmed() {
+    let pasted = read_code(&mut Cursor::new(" AQQcode \t\r\n"), || {}).unwrap();
+    assert_eq!(pasted.code, "AQQcode");
+
+    let pasted = read_code(&mut Cursor::new("http://localhost:8000/?code=AQQcode&state=csrf  \r\n"), || {});
+    let pasted = pasted.unwrap();
+    assert_eq!(pasted.code, "AQQcode");
+    assert_eq!(pasted.state.as_deref(), Some("csrf"));
+}
+
+
+#[test]
+fn pasted_code_is_sent_without_its_line_ending() {
+    let server = MockServer::start();
+    let endpoint = server.mock(|when, then| {
+        when.method(POST).path("/accessToken").x_www_form_urlencoded_tuple("code", "AQQcode");
+        then.status(200).body(r#"{"access_token": "AQVtoken"}"#);
+    });
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-server", "--no-cache"])
+        .args(["--token-url", &server.url("/accessToken")])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b" AQQcode \r\n").unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    endpoint.assert();
+}
+
+
+#[test]
+fn code_without_state_skips_the_csrf_check_loudly() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(r#"{"access_token": "AQVtoken"}"#);
+    });
+    let run = |extra_args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+            .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-cache"])
+            .args(["--code", "AQQcode", "--csrf", "csrf", "--token-url", &server.url("/accessToken")])
+            .args(["--session-file", env::temp_dir().join("lin_auth_session_no_state").to_str().unwrap()])
+            .args(extra_args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Warning: no state came back with the code"));
+
+    let output = run(&["--state", "csrf"]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8(output.stderr).unwrap().contains("no state came back"));
+}
+
+
+#[test]
+fn three_empty_lines_give_up() {
+    let err = read_code(&mut Cursor::new("\n\n\nAQQcode\n"), || {}).unwrap_err();
+    assert!(err.is::<EmptyCode>());
+}
+
+
+#[test]
+fn code_file_is_trimmed() {
+    let path = env::temp_dir().join(format!("lin_auth_code_{}", process::id()));
+    fs::write(&path, "http://localhost:8000/?code=AQQcode&state=csrf\n").unwrap();
+
+    let pasted = read_code_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(pasted.code, "AQQcode");
+    assert_eq!(pasted.state.as_deref(), Some("csrf"));
+}
+
+
+#[test]
+fn wait_timeout_exits_with_the_timeout_code() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "77abcdefgh1234", "--client-secret", "client-secret", "--no-cache",
+               "--no-server", "--wait-timeout", "1"])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_wait_timeout").to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Kept open, so the code never arrives.
+    let _stdin = child.stdin.take();
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(5), "{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{run_exchange, token_server};
+
+
+#[test]
+fn quiet_prints_only_the_token() {
+    let server = token_server(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+
+    let output = run_exchange(&server.url("/accessToken"), &["--quiet"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "AQVtoken\n");
+}
+
+
+#[test]
+fn pretty_indents_the_json_output() {
+    let server = token_server(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+
+    let output = run_exchange(&server.url("/accessToken"), &["--json", "--pretty"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\n  \"access_token\": \"AQVtoken\",\n  \"token_type\": \"Bearer\"\n}\n"
+    );
+}
+
+
+#[test]
+fn field_prints_only_that_value() {
+    let server = token_server(r#"{"access_token": "AQVtoken", "expires_in": 5184000, "token_type": "Bearer"}"#);
+
+    let output = run_exchange(&server.url("/accessToken"), &["--field", "expires_in"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "5184000\n");
+
+    let output = run_exchange(&server.url("/accessToken"), &["--field", "refresh_token"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("the token response has no refresh_token"));
+}
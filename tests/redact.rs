@@ -0,0 +1,66 @@
+use std::env;
+use std::process::Command;
+
+use httpmock::prelude::*;
+use lin_auth::{AuthMethod, curl_command};
+use lin_auth::redact::{mask_token, redact};
+use url::Url;
+
+
+#[test]
+fn url_in_error_loses_secrets() {
+    let error = "error sending request for url (https://www.linkedin.com/oauth/v2/accessToken\
+                 ?grant_type=authorization_code&code=AQQcode&client_id=app\
+                 &client_secret=s3cr3t): operation timed out";
+
+    let redacted = redact(error);
+    assert!(!redacted.contains("s3cr3t"));
+    assert!(!redacted.contains("AQQcode"));
+    assert!(redacted.contains("client_secret=***"));
+    assert!(redacted.contains("client_id=app"));
+    assert!(redacted.ends_with("operation timed out"));
+}
+
+
+#[test]
+fn report_masks_the_token_by_default() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(r#"{"access_token": "AQVsecrettokenx9f0", "token_type": "Bearer"}"#);
+    });
+    let run = |extra_args: &[&str]| Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-cache",
+               "--code", "AQQcode", "--csrf", "csrf", "--state", "csrf"])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_redact").to_str().unwrap()])
+        .args(["--token-url", &server.url("/accessToken")])
+        .args(extra_args)
+        .output()
+        .unwrap();
+
+    let masked = String::from_utf8(run(&[]).stdout).unwrap();
+    assert!(!masked.contains("AQVsecrettokenx9f0"));
+    assert!(masked.contains(&mask_token("AQVsecrettokenx9f0")));
+    assert_eq!(mask_token("AQVsecrettokenx9f0"), "AQVs...x9f0");
+
+    let shown = String::from_utf8(run(&["--show-token"]).stdout).unwrap();
+    assert!(shown.contains("AQVsecrettokenx9f0"));
+}
+
+
+#[test]
+fn curl_command_masks_the_credentials() {
+    let url = Url::parse("https://www.linkedin.com/oauth/v2/accessToken").unwrap();
+    let params = [("grant_type", "authorization_code"), ("code", "AQQcode"),
+                  ("client_id", "app"), ("client_secret", "it's s3cr3t")];
+
+    let body = curl_command(&url, &params, AuthMethod::Body, false);
+    assert!(body.starts_with("curl -X POST 'https://www.linkedin.com/oauth/v2/accessToken'"), "{}", body);
+    assert!(body.contains("--data-urlencode 'code=AQQcode'") && body.contains("'client_secret=***'"), "{}", body);
+
+    let basic = curl_command(&url, &params, AuthMethod::Basic, false);
+    assert!(basic.contains("-H 'Authorization: Basic ***'") && !basic.contains("client_secret"), "{}", basic);
+
+    let shown = curl_command(&url, &params, AuthMethod::Body, true);
+    assert!(shown.contains(r"'client_secret=it'\''s s3cr3t'"), "{}", shown);
+}
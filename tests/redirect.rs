@@ -0,0 +1,55 @@
+use lin_auth::AuthError;
+use lin_auth::redirect::{RedirectParams, parse_redirect};
+
+
+#[test]
+fn full_redirect_url_gives_code_and_state() {
+    let params = parse_redirect("http://localhost:8000/callback?code=AQQcode&state=csrf").unwrap();
+
+    assert_eq!(params, RedirectParams {
+        code: "AQQcode".to_string(),
+        state: Some("csrf".to_string()),
+        redirect_url: Some("http://localhost:8000/callback".to_string()),
+    });
+}
+
+
+#[test]
+fn bare_code_is_taken_as_is() {
+    let params = parse_redirect("AQQcode").unwrap();
+
+    assert_eq!(params, RedirectParams { code: "AQQcode".to_string(), state: None, redirect_url: None });
+}
+
+
+#[test]
+fn error_redirect_gives_the_oauth_error() {
+    let url = "https://localhost:8000/?error=user_cancelled_login\
+               &error_description=The+user+cancelled+LinkedIn+login&state=csrf";
+
+    match parse_redirect(url).unwrap_err() {
+        AuthError::OAuth { error, description } => {
+            assert_eq!(error, "user_cancelled_login");
+            assert_eq!(description, "The user cancelled LinkedIn login");
+        },
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+
+#[test]
+fn url_without_code_or_error_is_rejected() {
+    let err = parse_redirect("https://localhost:8000/callback?state=csrf").unwrap_err();
+    assert!(matches!(err, AuthError::MissingCode));
+}
+
+
+#[test]
+fn redirect_url_is_compared_without_its_query() {
+    let params = parse_redirect("http://localhost:8000/callback?code=AQQcode&state=csrf").unwrap();
+
+    assert!(params.redirect_matches("http://localhost:8000/callback"));
+    assert!(!params.redirect_matches("https://localhost:8000/callback"));
+    assert!(!params.redirect_matches("http://localhost:8000/"));
+    assert!(parse_redirect("AQQcode").unwrap().redirect_matches("http://localhost:8000/"));
+}
@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use lin_auth::retry::backoff;
+
+
+#[test]
+fn backoff_doubles_up_to_the_cap() {
+    let delays: Vec<Duration> = (0..8).map(|attempt| backoff(attempt, false)).collect();
+
+    assert_eq!(delays[..4], [500, 1000, 2000, 4000].map(Duration::from_millis));
+    assert_eq!(delays[7], Duration::from_secs(30));
+    assert_eq!(backoff(u32::MAX, false), Duration::from_secs(30));
+}
+
+
+#[test]
+fn jittered_backoff_stays_below_the_cap() {
+    for attempt in [0, 3, 10] {
+        let cap = backoff(attempt, false);
+        let delays: Vec<Duration> = (0..200).map(|_| backoff(attempt, true)).collect();
+
+        assert!(delays.iter().all(|delay| *delay <= cap), "{:?}", delays);
+        assert!(delays.iter().any(|delay| *delay < cap / 2), "{:?}", delays);
+    }
+}
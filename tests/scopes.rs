@@ -0,0 +1,124 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use httpmock::prelude::*;
+use lin_auth::scopes::{KNOWN_SCOPES, SCOPE_CATEGORIES, SCOPE_SETS, load_app_products, normalize_scopes,
+                       scope_set, split_scopes, validate_provisioned_scopes, validate_scopes};
+
+
+#[test]
+fn mixed_separators_and_repeated_flags() {
+    let values = ["r_ads,r_ads_reporting", "rw_ads r_ads", " openid , profile,,", "rw_ads"];
+
+    assert_eq!(
+        split_scopes(values.iter().copied()),
+        vec!["r_ads", "r_ads_reporting", "rw_ads", "openid", "profile"]
+    );
+}
+
+
+#[test]
+fn merged_scopes_are_normalized() {
+    let mut scopes: Vec<String> = scope_set("ads").unwrap().iter().map(|scope| scope.to_string()).collect();
+    scopes.extend(split_scopes(["R_ADS,OpenID", "custom_Scope"]));
+    let from_file = [" r_ads_reporting ", "", "  ", "openid", "custom_Scope"];
+    scopes.extend(from_file.iter().map(|scope| scope.to_string()));
+
+    normalize_scopes(&mut scopes);
+
+    assert_eq!(scopes, vec!["r_ads", "r_ads_reporting", "openid", "custom_Scope"]);
+}
+
+
+#[test]
+fn scopes_missing_from_the_grant_are_warned_about() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(r#"{"access_token": "AQVtoken", "token_type": "Bearer", "scope": "r_ads"}"#);
+    });
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-cache",
+               "--permissions", "r_ads,r_ads_reporting", "--code", "AQQcode", "--csrf", "csrf", "--state", "csrf"])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_scopes").to_str().unwrap()])
+        .args(["--token-url", &server.url("/accessToken")])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("did not grant r_ads_reporting,"), "{}", stderr);
+}
+
+
+#[test]
+fn scope_set_help_lists_every_preset_scope() {
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth")).arg("--help").output().unwrap();
+    let help = String::from_utf8(output.stdout).unwrap();
+    let help = &help[help.find("--scope-set").unwrap()..];
+
+    for (set, scopes) in SCOPE_SETS {
+        let listed = &help[help.find(&format!("'{}' (", set)).unwrap()..];
+        let listed = &listed[..listed.find(')').unwrap()];
+        for scope in *scopes {
+            assert!(listed.contains(scope), "{} is missing from '{}'", scope, set);
+        }
+    }
+}
+
+
+#[test]
+fn every_known_scope_has_a_menu_category() {
+    let mut categorized: Vec<&str> = SCOPE_CATEGORIES.iter().flat_map(|(_, scopes)| scopes.iter().copied()).collect();
+    let mut known = KNOWN_SCOPES.to_vec();
+    categorized.sort_unstable();
+    known.sort_unstable();
+
+    assert_eq!(categorized, known);
+}
+
+
+#[test]
+fn app_products_expand_to_their_permissions() {
+    let path = env::temp_dir().join("lin_auth_app_products_expand");
+    let contents = "# products\nsign in with linkedin using openid connect\nR_ADS, r_basicprofile\n";
+    fs::write(&path, contents).unwrap();
+
+    let provisioned = load_app_products(&path).unwrap();
+    assert_eq!(provisioned, vec!["openid", "profile", "email", "r_ads", "r_basicprofile"]);
+    assert!(validate_provisioned_scopes(&["openid", "r_ads"], &provisioned).is_ok());
+    let err = validate_provisioned_scopes(&["openid", "r_ads_reporting"], &provisioned).unwrap_err();
+    assert_eq!(err.scopes, vec!["r_ads_reporting"]);
+    assert_eq!(validate_scopes(&["openid", "r_adz"]).unwrap_err().scopes, vec!["r_adz"]);
+}
+
+
+#[test]
+fn misspelled_app_product_is_reported_with_its_line() {
+    let path = env::temp_dir().join("lin_auth_app_products_misspelled");
+    fs::write(&path, "Sign In with LinkedIn using OpenID Connect\nAdvertizing API\n").unwrap();
+
+    let err = load_app_products(&path).unwrap_err();
+    assert!(err.reason.starts_with("line 2: 'Advertizing API' is neither"), "{}", err);
+}
+
+
+#[test]
+fn scopes_outside_the_app_products_are_rejected_before_the_url() {
+    let path = env::temp_dir().join("lin_auth_app_products_reject");
+    fs::write(&path, "Sign In with LinkedIn using OpenID Connect\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "77abcdefgh1234", "--permissions", "openid,r_ads_reporting", "--dry-run"])
+        .args(["--app-products", path.to_str().unwrap(), "--allow-unknown-scopes"])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_app_products").to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("the app is not provisioned for: r_ads_reporting,"), "{}", stderr);
+}
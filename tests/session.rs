@@ -0,0 +1,58 @@
+use std::env;
+use std::process::{self, Command};
+
+use httpmock::prelude::*;
+use url::Url;
+
+
+#[test]
+fn split_invocations_share_the_state_and_verifier() {
+    let server = MockServer::start();
+    let session = env::temp_dir().join(format!("lin_auth_session_{}", process::id()));
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "-p", "r_ads", "-r", "http://localhost:8000/callback", "--no-cache"])
+        .args(["--session-file", session.to_str().unwrap()])
+        .args(args)
+        .output()
+        .unwrap();
+
+    let generated = run(&["--dry-run", "--pkce"]);
+    assert!(generated.status.success());
+    let url = Url::parse(String::from_utf8(generated.stdout).unwrap().trim()).unwrap();
+    let state = url.query_pairs().find(|(key, _)| key == "state").unwrap().1.into_owned();
+
+    let endpoint = server.mock(|when, then| {
+        when.method(POST)
+            .path("/accessToken")
+            .x_www_form_urlencoded_tuple("state", &state)
+            .x_www_form_urlencoded_key_exists("code_verifier");
+        then.status(200).body(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+    });
+    let exchanged = run(&["--quiet", "--code", "AQQcode", "--state", &state,
+                          "--token-url", &server.url("/accessToken")]);
+
+    assert!(exchanged.status.success(), "{}", String::from_utf8_lossy(&exchanged.stderr));
+    endpoint.assert();
+    assert!(!session.exists());
+}
+
+
+#[test]
+fn state_may_start_with_a_hyphen() {
+    let server = MockServer::start();
+    let endpoint = server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+    });
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-cache", "--quiet"])
+        .args(["--code", "AQQcode", "--csrf", "-iXcsrf", "--state", "-iXcsrf"])
+        .args(["--session-file", env::temp_dir().join("lin_auth_session_hyphen").to_str().unwrap()])
+        .args(["--token-url", &server.url("/accessToken")])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    endpoint.assert();
+}
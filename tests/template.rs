@@ -0,0 +1,21 @@
+use lin_auth::TokenResponse;
+use lin_auth::template::{UnknownPlaceholder, render_token};
+
+
+fn token() -> TokenResponse {
+    serde_json::from_str(r#"{"access_token": "AQV", "expires_in": 5183999}"#).unwrap()
+}
+
+
+#[test]
+fn placeholders_are_substituted() {
+    let rendered = render_token("Bearer {access_token} ({expires_in}s){refresh_token}", &token()).unwrap();
+    assert_eq!(rendered, "Bearer AQV (5183999s)");
+}
+
+
+#[test]
+fn unknown_placeholder_is_rejected() {
+    let err = render_token("{access_token} {token_type}", &token()).unwrap_err();
+    assert!(matches!(err, UnknownPlaceholder(name) if name == "token_type"));
+}
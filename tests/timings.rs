@@ -0,0 +1,22 @@
+mod common;
+
+use serde_json::Value;
+
+use common::{run_exchange, token_server};
+
+
+#[test]
+fn json_timings_name_each_phase() {
+    let server = token_server(r#"{"access_token": "AQVtoken", "token_type": "Bearer"}"#);
+
+    let output = run_exchange(&server.url("/accessToken"), &["--json", "--timings"]);
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let timings: Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert!(timings["timings"]["connect"].is_u64(), "{}", stderr);
+    assert!(timings["timings"]["token"].is_u64(), "{}", stderr);
+
+    let stdout: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stdout["access_token"], "AQVtoken");
+}
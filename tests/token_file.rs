@@ -0,0 +1,48 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{self, Command, Stdio};
+
+use httpmock::prelude::*;
+
+use lin_auth::TokenResponse;
+
+
+#[test]
+fn output_writes_a_private_token_file() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(POST).path("/accessToken");
+        then.status(200).body(
+            r#"{"access_token": "AQVtoken", "expires_in": 5184000,
+                "refresh_token": "AQXrefresh", "scope": "r_ads"}"#
+        );
+    });
+    let path = env::temp_dir().join(format!("lin_auth_token_{}.json", process::id()));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["--client-id", "client-id", "--client-secret", "client-secret", "--no-server", "--no-cache"])
+        .args(["--token-url", &server.url("/accessToken"), "--output", path.to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"AQQcode\n").unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+    let token: TokenResponse = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(token.access_token, "AQVtoken");
+    assert_eq!(token.expires_in, Some(5184000));
+    assert_eq!(token.refresh_token.as_deref(), Some("AQXrefresh"));
+    assert_eq!(token.scope.as_deref(), Some("r_ads"));
+}
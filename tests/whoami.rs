@@ -0,0 +1,60 @@
+use std::env;
+use std::fs;
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use httpmock::prelude::*;
+
+
+fn whoami(server: &MockServer, name: &str, expires_at: u64) -> Output {
+    let path = env::temp_dir().join(format!("lin_auth_whoami_{}.json", name));
+    let token = format!(r#"{{"access_token": "AQVtoken", "expires_in": 5184000, "expires_at": {}}}"#, expires_at);
+    fs::write(&path, token).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lin_auth"))
+        .args(["whoami", "--client-id", "78abcd1234efgh", "--token-file", path.to_str().unwrap()])
+        .args(["--api-url", &server.url("/")])
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+
+#[test]
+fn token_file_reports_the_time_left() {
+    let server = MockServer::start();
+    server.mock(|when, then| {
+        when.method(GET).path("/me").header("Authorization", "Bearer AQVtoken");
+        then.status(200).body(r#"{"id": "abc123", "localizedFirstName": "Ada", "localizedLastName": "Lovelace"}"#);
+    });
+
+    let output = whoami(&server, "live", unix_now() + 2 * 3600 + 60);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("Signed in as Ada Lovelace (abc123)."), "{}", stdout);
+    assert!(stdout.contains("/ in 2 hours."), "{}", stdout);
+}
+
+
+#[test]
+fn expired_token_file_is_not_sent() {
+    let server = MockServer::start();
+    let member = server.mock(|when, then| {
+        when.method(GET).path("/me");
+        then.status(401);
+    });
+
+    let output = whoami(&server, "expired", unix_now() - 60);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("the stored access key has expired"), "{}", stderr);
+    assert_eq!(member.hits(), 0);
+}